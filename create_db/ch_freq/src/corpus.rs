@@ -0,0 +1,247 @@
+//! Builds word and character frequency lists from a local corpus of plain text or SRT files, e.g.
+//! technical manuals of a domain. The output has the same `FreqRow` format as the SUBTLEX lists.
+//!
+//! The text is segmented with the headwords of the dictionary in `db.json` by forward maximum
+//! matching. Every file counts as one context for the contextual diversity.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use calamine::Error;
+use serde::Deserialize;
+
+use crate::FreqRow;
+
+const USAGE: &str =
+    "Usage: ch_freq corpus [--db ../create_json/db.json] [--out corpus] <files or folders>...";
+
+pub fn run(args: &[String]) -> Result<(), Error> {
+    let mut db_path = "../create_json/db.json".to_string();
+    let mut out_dir = "corpus".to_string();
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" => db_path = args.next().ok_or(Error::Msg(USAGE))?.to_string(),
+            "--out" => out_dir = args.next().ok_or(Error::Msg(USAGE))?.to_string(),
+            path => inputs.push(PathBuf::from(path)),
+        }
+    }
+    if inputs.is_empty() {
+        return Err(Error::Msg(USAGE));
+    }
+
+    let segmenter = Segmenter::from_db(&db_path)?;
+    let mut files = Vec::new();
+    for input in &inputs {
+        collect_files(input, &mut files)?;
+    }
+    println!("Counting {} files", files.len());
+
+    let mut word_counts = Counts::default();
+    let mut char_counts = Counts::default();
+    for file in &files {
+        let Ok(text) = std::fs::read_to_string(file) else {
+            println!("Skipping {:?}, not valid UTF-8", file);
+            continue;
+        };
+        let text = if is_srt(file) { strip_srt(&text) } else { text };
+        let words = segmenter.segment(&text);
+        word_counts.add_context(words.iter().copied());
+        char_counts.add_context(words.iter().flat_map(|word| {
+            word.char_indices()
+                .map(|(pos, cha)| &word[pos..pos + cha.len_utf8()])
+        }));
+    }
+
+    std::fs::create_dir_all(&out_dir)?;
+    let out_dir = Path::new(&out_dir);
+    word_counts.write(&out_dir.join("word_freq.json"))?;
+    char_counts.write(&out_dir.join("char_freq.json"))?;
+    Ok(())
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else if matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("txt" | "srt")
+    ) {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn is_srt(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("srt")
+}
+
+/// Removes the subtitle numbers and timestamps of an SRT file
+fn strip_srt(text: &str) -> String {
+    text.lines()
+        .filter(|line| {
+            let line = line.trim();
+            !line.contains("-->") && !line.chars().all(|cha| cha.is_ascii_digit())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_chinese(cha: char) -> bool {
+    matches!(cha as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF)
+}
+
+#[derive(Deserialize)]
+struct DbEntry {
+    simplified: String,
+    traditional: String,
+}
+
+/// Segments chinese text into the words of the dictionary
+struct Segmenter {
+    words: HashSet<String>,
+    max_word_len: usize,
+}
+
+impl Segmenter {
+    fn from_db(path: &str) -> Result<Self, Error> {
+        let data = std::fs::read_to_string(path)?;
+        let words: HashSet<String> = data
+            .lines()
+            .flat_map(|line| {
+                let entry: DbEntry = serde_json::from_str(line).unwrap();
+                [entry.simplified, entry.traditional]
+            })
+            .collect();
+        Ok(Self::new(words))
+    }
+
+    fn new(words: HashSet<String>) -> Self {
+        let max_word_len = words
+            .iter()
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or(1)
+            .min(8);
+        Self {
+            words,
+            max_word_len,
+        }
+    }
+
+    /// Forward maximum matching on the runs of chinese characters. Other text is skipped,
+    /// characters without a dictionary entry are returned as single words.
+    fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut words = Vec::new();
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let byte_pos = |index: usize| chars.get(index).map(|el| el.0).unwrap_or(text.len());
+        let mut index = 0;
+        while index < chars.len() {
+            if !is_chinese(chars[index].1) {
+                index += 1;
+                continue;
+            }
+            let run_len = chars[index..]
+                .iter()
+                .take(self.max_word_len)
+                .take_while(|(_, cha)| is_chinese(*cha))
+                .count();
+            let word_len = (1..=run_len)
+                .rev()
+                .find(|len| {
+                    *len == 1
+                        || self
+                            .words
+                            .contains(&text[byte_pos(index)..byte_pos(index + len)])
+                })
+                .unwrap_or(1);
+            words.push(&text[byte_pos(index)..byte_pos(index + word_len)]);
+            index += word_len;
+        }
+        words
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    // text -> (count, number of contexts)
+    counts: HashMap<String, (u64, u64)>,
+    total: u64,
+    num_contexts: u64,
+}
+
+impl Counts {
+    fn add_context<'a>(&mut self, texts: impl Iterator<Item = &'a str>) {
+        self.num_contexts += 1;
+        let mut seen = HashSet::new();
+        for text in texts {
+            self.total += 1;
+            let (count, contexts) = self.counts.entry(text.to_string()).or_default();
+            *count += 1;
+            if seen.insert(text) {
+                *contexts += 1;
+            }
+        }
+    }
+
+    /// Writes the counts in the `FreqRow` format, most frequent first
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        let mut rows: Vec<FreqRow> = self
+            .counts
+            .iter()
+            .map(|(text, (count, contexts))| FreqRow {
+                text: text.to_string(),
+                count: *count,
+                count_per_million: *count as f64 / self.total as f64 * 1_000_000.0,
+                // log10 without smoothing, like logW and logCD of SUBTLEX-CH
+                log_count: (*count as f64).log10(),
+                cd: *contexts as f64,
+                cd_percentage: *contexts as f64 / self.num_contexts as f64 * 100.0,
+                log_cd: (*contexts as f64).log10(),
+            })
+            .collect();
+        rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+
+        let mut fs = std::fs::File::create(path)?;
+        for row in rows {
+            fs.write_all(serde_json::to_string(&row).unwrap().as_bytes())?;
+            fs.write_all(b"\n")?;
+        }
+        println!("Wrote {:?}", path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment() {
+        let words = ["我", "想", "去", "台北", "台北市"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let segmenter = Segmenter::new(words);
+        assert_eq!(
+            segmenter.segment("我想去台北。OK 台北市"),
+            vec!["我", "想", "去", "台北", "台北市"]
+        );
+    }
+
+    #[test]
+    fn test_strip_srt() {
+        let srt =
+            "1\n00:00:01,000 --> 00:00:02,000\n你好\n\n2\n00:00:03,000 --> 00:00:04,000\n再見";
+        assert_eq!(
+            strip_srt(srt).split_whitespace().collect::<Vec<_>>(),
+            vec!["你好", "再見"]
+        );
+    }
+}
@@ -1,11 +1,233 @@
-use std::io::Write;
+use std::{collections::HashMap, io::Write, path::Path};
 
-use calamine::{open_workbook, Error, RangeDeserializerBuilder, Reader, Xlsx};
+use calamine::{
+    open_workbook, open_workbook_auto, DataType, Error, RangeDeserializerBuilder, Reader, Xlsx,
+};
 use serde::{Deserialize, Serialize};
 
+mod corpus;
+
+/// Without arguments the SUBTLEX-CH lists are converted, `ch_freq corpus <files or folders>...`
+/// builds the frequency lists from a local corpus, see [`corpus`].
+///
+/// Not all input files are in the repository (e.g. `SUBTLEX-CH-WF.xlsx`), missing ones are
+/// skipped and the other lists are still converted.
 fn main() -> Result<(), Error> {
-    convert("SUBTLEX-CH-WF.xlsx", "SUBTLEX-CH-WF", "word_freq.json")?;
-    convert("SUBTLEX-CH-CHR.xlsx", "SUBTLEX-CH-CHR", "char_freq.json")?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("corpus") {
+        return corpus::run(&args[1..]);
+    }
+    if Path::new("SUBTLEX-CH-WF.xlsx").exists() {
+        convert("SUBTLEX-CH-WF.xlsx", "SUBTLEX-CH-WF", "word_freq.json")?;
+    } else if input_exists(POS_FILE) {
+        println!("Using the lemma counts of {} for word_freq.json", POS_FILE);
+        convert_pos_freq(POS_FILE, "word_freq.json")?;
+    }
+    if input_exists("SUBTLEX-CH-CHR.xlsx") {
+        convert("SUBTLEX-CH-CHR.xlsx", "SUBTLEX-CH-CHR", "char_freq.json")?;
+    }
+    if input_exists(POS_FILE) {
+        convert_pos(POS_FILE, "word_pos.json")?;
+    }
+    if input_exists(CLASSICAL_FILE) {
+        convert_classical(CLASSICAL_FILE, "classical_char_freq.json")?;
+    }
+    Ok(())
+}
+
+const POS_FILE: &str = "SUBTLEX-CH-WF_PoS/SUBTLEX-CH-WF_PoS";
+const CLASSICAL_FILE: &str = "CharFreq-Classical_mts_edu.xls";
+
+fn input_exists(file: &str) -> bool {
+    let exists = Path::new(file).exists();
+    if !exists {
+        println!("Skipping missing {}", file);
+    }
+    exists
+}
+
+/// Maps the PKU/ICTCLAS part-of-speech labels to readable categories.
+/// See `Labels used in the PKU PoS system.doc`
+fn pos_category(label: &str) -> Option<&'static str> {
+    let category = match label {
+        "n" | "vn" => "noun",
+        "nr" | "ns" | "nt" | "nz" => "proper_noun",
+        "v" => "verb",
+        "a" | "an" | "ad" | "b" | "z" => "adjective",
+        "d" => "adverb",
+        "m" | "mq" | "Mg" => "numeral",
+        "q" | "qt" | "qv" => "measure_word",
+        "r" | "Rg" => "pronoun",
+        "p" => "preposition",
+        "c" | "cc" => "conjunction",
+        "u" | "y" => "particle",
+        "e" => "interjection",
+        "o" => "onomatopoeia",
+        "t" | "tg" => "time",
+        "s" | "f" => "location",
+        "l" => "expression",
+        "h" | "k" => "affix",
+        "g" => "morpheme",
+        _ => return None,
+    };
+    Some(category)
+}
+
+/// Converts the GB18030 encoded SUBTLEX-CH-WF_PoS file to a part-of-speech distribution per word.
+///
+/// The file has a line per lemma, followed by a line per part of speech:
+/// ```text
+/// 一	203626	-	-
+/// @	@	一	m	197747
+/// @	@	一	d	5666
+/// ```
+#[allow(clippy::tabs_in_doc_comments)]
+fn convert_pos(file: &str, out_file: &str) -> Result<(), Error> {
+    let bytes = std::fs::read(file)?;
+    let (text, _, _) = encoding_rs::GB18030.decode(&bytes);
+
+    let mut rows: Vec<PosRow> = Vec::new();
+    // Skip total word count and header
+    for line in text.lines().skip(2) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields.as_slice() {
+            ["@", _, _, label, count, ..] => {
+                let (Some(row), Some(category)) = (rows.last_mut(), pos_category(label)) else {
+                    continue;
+                };
+                let count: u64 = count.trim().parse().unwrap_or(0);
+                *row.counts.entry(category.to_string()).or_default() += count;
+            }
+            [lemma, ..] if !lemma.trim().is_empty() => rows.push(PosRow {
+                text: lemma.trim().to_string(),
+                ..Default::default()
+            }),
+            _ => {}
+        }
+    }
+
+    let mut fs = std::fs::File::create(out_file).unwrap();
+    for row in rows {
+        let total: u64 = row.counts.values().sum();
+        if total == 0 {
+            continue;
+        }
+        // Shares are rounded to 0.001, smaller shares are dropped
+        let mut pos: Vec<PosShare> = row
+            .counts
+            .into_iter()
+            .map(|(pos, count)| PosShare {
+                pos,
+                share: (count as f64 / total as f64 * 1000.0).round() / 1000.0,
+            })
+            .filter(|pos| pos.share > 0.0)
+            .collect();
+        // Ties are sorted by name, the counts come from a HashMap
+        pos.sort_by(|a, b| b.share.total_cmp(&a.share).then_with(|| a.pos.cmp(&b.pos)));
+        let word_pos = WordPos {
+            text: row.text,
+            pos,
+        };
+        fs.write_all(serde_json::to_string(&word_pos).unwrap().as_bytes())
+            .unwrap();
+        fs.write_all(b"\n").unwrap();
+    }
+
+    Ok(())
+}
+
+/// Builds the word frequency list from the lemma counts of the SUBTLEX-CH-WF_PoS file (see
+/// [`convert_pos`]), if `SUBTLEX-CH-WF.xlsx` is missing. The file has no contextual diversity,
+/// so the CD fields are left out.
+fn convert_pos_freq(file: &str, out_file: &str) -> Result<(), Error> {
+    let bytes = std::fs::read(file)?;
+    let (text, _, _) = encoding_rs::GB18030.decode(&bytes);
+    let mut lines = text.lines();
+    // E.g. "Total word count: 33,546,516"
+    let total: u64 = lines
+        .next()
+        .and_then(|line| line.split(':').nth(1))
+        .map(|total| {
+            total
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect::<String>()
+        })
+        .and_then(|total| total.parse().ok())
+        .ok_or(Error::Msg("Missing total word count"))?;
+
+    let mut fs = std::fs::File::create(out_file).unwrap();
+    // Skip header
+    for line in lines.skip(1) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [lemma, count, ..] = fields.as_slice() else {
+            continue;
+        };
+        let (lemma, Ok(count)) = (lemma.trim(), count.trim().parse::<u64>()) else {
+            continue;
+        };
+        if lemma.is_empty() || lemma == "@" || count == 0 {
+            continue;
+        }
+        let row = LemmaFreqRow {
+            text: lemma.to_string(),
+            count,
+            count_per_million: (count as f64 / total as f64 * 1_000_000.0 * 100.0).round() / 100.0,
+            log_count: ((count as f64).log10() * 10000.0).round() / 10000.0,
+        };
+        fs.write_all(serde_json::to_string(&row).unwrap().as_bytes())
+            .unwrap();
+        fs.write_all(b"\n").unwrap();
+    }
+
+    Ok(())
+}
+
+/// Converts the character frequencies of classical chinese texts (Jun Da, mts.edu), sheet "dj".
+///
+/// Columns: 序号, 汉字, 频率, 百分比 (cumulative), 拼音 (e.g. "bu4/bu2"), 英文
+fn convert_classical(file: &str, out_file: &str) -> Result<(), Error> {
+    let mut workbook = open_workbook_auto(file)?;
+    let range = workbook
+        .worksheet_range("dj")
+        .ok_or(Error::Msg("Cannot find workbook"))??;
+
+    let rows: Vec<(String, u64, String)> = range
+        .rows()
+        .skip(1)
+        .filter_map(|row| {
+            let count = match row.get(2)? {
+                DataType::Int(count) => *count as u64,
+                DataType::Float(count) => *count as u64,
+                _ => return None,
+            };
+            let pinyin = row.get(4).and_then(|el| el.get_string()).unwrap_or("");
+            Some((
+                row.get(1)?.get_string()?.trim().to_string(),
+                count,
+                pinyin.trim().to_string(),
+            ))
+        })
+        .collect();
+    let total: u64 = rows.iter().map(|row| row.1).sum();
+
+    let mut fs = std::fs::File::create(out_file).unwrap();
+    for (text, count, pinyin) in rows {
+        let row = ClassicalFreqRow {
+            text,
+            count,
+            count_per_million: (count as f64 / total as f64 * 1_000_000.0 * 100.0).round() / 100.0,
+            pinyin: pinyin
+                .split('/')
+                .map(|pinyin| pinyin.trim().to_string())
+                .filter(|pinyin| !pinyin.is_empty())
+                .collect(),
+        };
+        fs.write_all(serde_json::to_string(&row).unwrap().as_bytes())
+            .unwrap();
+        fs.write_all(b"\n").unwrap();
+    }
+
     Ok(())
 }
 
@@ -47,3 +269,39 @@ struct FreqRow {
     cd_percentage: f64,
     log_cd: f64,
 }
+
+/// Like `FreqRow` without the contextual diversity
+#[derive(Serialize, Deserialize, Debug)]
+struct LemmaFreqRow {
+    text: String,
+    count: u64,
+    count_per_million: f64,
+    log_count: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ClassicalFreqRow {
+    text: String,
+    count: u64,
+    count_per_million: f64,
+    // Readings with tone numbers, e.g. ["bu4", "bu2"]
+    pinyin: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct PosRow {
+    text: String,
+    counts: HashMap<String, u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct WordPos {
+    text: String,
+    pos: Vec<PosShare>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PosShare {
+    pos: String,
+    share: f64,
+}
@@ -0,0 +1,152 @@
+//! Levels of the Children's Chinese Competency Certification (CCCC, 兒童華語文能力測驗)
+//! vocabulary list from `CCCC_Vocabulary_2022.xls`.
+//!
+//! The words of a level are in the sheets 萌芽級 (1), 成長級 (2) and 茁壯級 (3). The cells contain
+//! alternatives and optional parts, e.g. `哥(哥)` or `女(生)/女孩`, which are expanded to all
+//! forms.
+
+use std::collections::{HashMap, HashSet};
+
+use calamine::{open_workbook_auto, DataType, Reader};
+
+const CCCC_PATH: &str = "CCCC_Vocabulary_2022.xls";
+
+/// Level sheets, ordered by level
+const LEVEL_SHEETS: [&str; 3] = ["萌芽級", "成長級", "茁壯級"];
+
+#[derive(Debug, Default)]
+struct CcccWord {
+    level: u32,
+    // Toneless pinyin of all forms, e.g. "baba" and "ba" for 爸(爸)
+    pinyin: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct CcccDictionary {
+    words: HashMap<String, CcccWord>,
+}
+
+impl CcccDictionary {
+    /// Returns the level of the word. Words with multiple readings need to match the pinyin
+    /// (ignoring tones, since the list misses most neutral tones).
+    pub fn get_level(
+        &self,
+        traditional: &str,
+        pinyin_ws_tone_number: &str,
+        is_unambiguous: bool,
+    ) -> Option<u32> {
+        let word = self.words.get(traditional)?;
+        if is_unambiguous
+            || word
+                .pinyin
+                .contains(&toneless_pinyin(pinyin_ws_tone_number))
+        {
+            Some(word.level)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn load_cccc_dictionary() -> CcccDictionary {
+    let mut dict = CcccDictionary::default();
+    if !std::path::Path::new(CCCC_PATH).exists() {
+        println!("Skipping missing CCCC vocabulary {}", CCCC_PATH);
+        return dict;
+    }
+    let mut workbook = open_workbook_auto(CCCC_PATH).unwrap();
+    for (level, sheet) in (1..).zip(LEVEL_SHEETS) {
+        let range = workbook.worksheet_range(sheet).unwrap().unwrap();
+        // Skip title and header
+        for row in range.rows().skip(2) {
+            let (Some(DataType::String(traditional)), Some(DataType::String(pinyin))) =
+                (row.get(2), row.get(4))
+            else {
+                continue;
+            };
+            let pinyin: Vec<String> = expand_forms(pinyin)
+                .iter()
+                .map(|pinyin| toneless_pinyin(pinyin))
+                .collect();
+            for form in expand_forms(traditional) {
+                let word = dict.words.entry(form).or_insert_with(|| CcccWord {
+                    level,
+                    pinyin: HashSet::new(),
+                });
+                word.level = word.level.min(level);
+                word.pinyin.extend(pinyin.iter().cloned());
+            }
+        }
+    }
+    dict
+}
+
+/// Expands alternatives and optional parts, e.g. "女(生)/女孩" to "女生", "女" and "女孩"
+pub fn expand_forms(cell: &str) -> Vec<String> {
+    let mut forms = Vec::new();
+    for alternative in cell.split('/') {
+        let alternative = alternative.trim();
+        if alternative.is_empty() {
+            continue;
+        }
+        let with_optional: String = alternative
+            .chars()
+            .filter(|cha| !matches!(cha, '(' | ')' | '（' | '）'))
+            .collect();
+        let without_optional = remove_optional_parts(alternative);
+        forms.push(with_optional.clone());
+        if without_optional != with_optional && !without_optional.is_empty() {
+            forms.push(without_optional);
+        }
+    }
+    forms
+}
+
+fn remove_optional_parts(text: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    for cha in text.chars() {
+        match cha {
+            '(' | '（' => depth += 1,
+            ')' | '）' => depth -= 1,
+            _ if depth == 0 => result.push(cha),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Pinyin without tones and whitespace, e.g. "jiě jie", "jie3 jie5" and "nǚ" to "jiejie" and "nu"
+pub fn toneless_pinyin(pinyin: &str) -> String {
+    pinyin
+        .to_lowercase()
+        .replace("u:", "u")
+        .chars()
+        .filter(|cha| !cha.is_whitespace() && !cha.is_ascii_digit())
+        .map(remove_tone_mark)
+        .collect()
+}
+
+fn remove_tone_mark(cha: char) -> char {
+    match cha {
+        'ā' | 'á' | 'ǎ' | 'à' | 'ă' => 'a',
+        'ē' | 'é' | 'ě' | 'è' | 'ĕ' => 'e',
+        'ī' | 'í' | 'ǐ' | 'ì' | 'ĭ' => 'i',
+        'ō' | 'ó' | 'ǒ' | 'ò' | 'ŏ' => 'o',
+        'ū' | 'ú' | 'ǔ' | 'ù' | 'ŭ' | 'ü' | 'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' | 'v' => 'u',
+        _ => cha,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_forms() {
+        assert_eq!(expand_forms("女(生)/女孩"), vec!["女生", "女", "女孩"]);
+        assert_eq!(expand_forms("名字"), vec!["名字"]);
+        assert_eq!(toneless_pinyin("nǚhái"), "nuhai");
+        assert_eq!(toneless_pinyin("nu:3 sheng1"), "nusheng");
+    }
+}
@@ -0,0 +1,142 @@
+//! Detection of four-character idioms (成語).
+//!
+//! An entry is an idiom if one of its definitions is marked with `(idiom)`, has glosses starting
+//! with `lit.` and `fig.`, or the word is in the local list `chengyu.txt` (optional, one idiom
+//! per line).
+
+use std::collections::HashSet;
+
+pub const CHENGYU_PATH: &str = "chengyu.txt";
+
+pub fn load_chengyu_list(path: &str) -> HashSet<String> {
+    if !std::path::Path::new(path).exists() {
+        println!("Skipping missing chengyu list {}", path);
+        return HashSet::new();
+    }
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+pub fn is_chengyu(
+    traditional: &str,
+    simplified: &str,
+    meanings: &[String],
+    chengyu_list: &HashSet<String>,
+) -> bool {
+    if traditional.chars().count() != 4 {
+        return false;
+    }
+    let has_idiom_marker = meanings.iter().any(|meaning| meaning.contains("(idiom)"));
+    let glosses = split_glosses(meanings);
+    let has_lit = glosses.iter().any(|gloss| has_marker(gloss, "lit."));
+    let has_fig = glosses.iter().any(|gloss| has_marker(gloss, "fig."));
+    has_idiom_marker
+        || (has_lit && has_fig)
+        || chengyu_list.contains(traditional)
+        || chengyu_list.contains(simplified)
+}
+
+/// The glosses of the definitions, which are separated by `;`
+fn split_glosses(meanings: &[String]) -> Vec<&str> {
+    meanings
+        .iter()
+        .flat_map(|meaning| meaning.split(';'))
+        .map(str::trim)
+        .filter(|gloss| !gloss.is_empty())
+        .collect()
+}
+
+/// True if the gloss starts with the marker as a word, e.g. "lit." but not "split."
+fn has_marker(gloss: &str, marker: &str) -> bool {
+    gloss.split_whitespace().next() == Some(marker)
+}
+
+/// Splits the definitions into literal and figurative meanings, e.g.
+/// "lit. draw legs on a snake (idiom); fig. to ruin the effect by adding sth superfluous"
+///
+/// Definitions without a marker next to a literal meaning are figurative, e.g.
+/// "lit. one stone, two birds (idiom); to kill two birds with one stone".
+/// Returns empty lists if there is no literal meaning.
+pub fn split_literal_figurative(meanings: &[String]) -> (Vec<String>, Vec<String>) {
+    let parts = split_glosses(meanings);
+    if !parts.iter().any(|part| has_marker(part, "lit.")) {
+        return (Vec::new(), Vec::new());
+    }
+    let clean = |part: &str| part.replace("(idiom)", "").trim().to_string();
+    let mut literal = Vec::new();
+    let mut figurative = Vec::new();
+    for part in parts {
+        if has_marker(part, "lit.") {
+            literal.push(clean(&part["lit.".len()..]));
+        } else if has_marker(part, "fig.") {
+            figurative.push(clean(&part["fig.".len()..]));
+        } else if part != "(idiom)" {
+            figurative.push(clean(part));
+        }
+    }
+    (literal, figurative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_literal_figurative() {
+        let meanings = vec![
+            "lit. draw legs on a snake (idiom); fig. to ruin the effect by adding sth superfluous"
+                .to_string(),
+        ];
+        assert_eq!(
+            split_literal_figurative(&meanings),
+            (
+                vec!["draw legs on a snake".to_string()],
+                vec!["to ruin the effect by adding sth superfluous".to_string()]
+            )
+        );
+        let meanings = vec![
+            "lit. one stone, two birds (idiom)".to_string(),
+            "to kill two birds with one stone".to_string(),
+        ];
+        assert_eq!(
+            split_literal_figurative(&meanings).1,
+            vec!["to kill two birds with one stone".to_string()]
+        );
+        assert!(is_chengyu(
+            "一石二鳥",
+            "一石二鸟",
+            &meanings,
+            &HashSet::new()
+        ));
+    }
+
+    #[test]
+    fn test_is_chengyu_markers() {
+        // "lit." and "fig." only count at the start of a gloss
+        let meanings = vec![
+            "to make a split. decision".to_string(),
+            "a fig. tree".to_string(),
+        ];
+        assert!(!is_chengyu(
+            "四個字詞",
+            "四个字词",
+            &meanings,
+            &HashSet::new()
+        ));
+        assert_eq!(
+            split_literal_figurative(&meanings),
+            (Vec::new(), Vec::new())
+        );
+        let meanings = vec!["lit. to add legs; fig. to overdo".to_string()];
+        assert!(is_chengyu(
+            "畫蛇添足",
+            "画蛇添足",
+            &meanings,
+            &HashSet::new()
+        ));
+    }
+}
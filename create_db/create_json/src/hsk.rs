@@ -0,0 +1,117 @@
+//! HSK levels from local word lists, HSK 3.0 (levels 1 to 9) and the legacy HSK 2.0 (levels 1
+//! to 6).
+//!
+//! The lists are CSV files with the columns `Level,Simplified,Pinyin`, e.g. `1,爱,ài`. The pinyin
+//! may use tone marks or tone numbers. HSK 3.0 lists the advanced levels as one band `7-9`, which
+//! is stored as level 7 and tagged with all levels of the band.
+
+use std::collections::HashMap;
+
+use prettify_pinyin::prettify;
+use serde::Deserialize;
+
+pub const HSK3_PATH: &str = "hsk3.csv";
+pub const HSK2_PATH: &str = "hsk2.csv";
+
+#[derive(Debug, Deserialize)]
+struct HskRow {
+    #[serde(rename = "Level")]
+    level: String,
+    #[serde(rename = "Simplified")]
+    simplified: String,
+    #[serde(rename = "Pinyin")]
+    pinyin: String,
+}
+
+/// A level or a band of levels, e.g. 3 to 3 or 7 to 9
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HskLevels {
+    pub first: u32,
+    pub last: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct HskDictionary {
+    // Simplified -> (normalized pinyin, levels)
+    words: HashMap<String, Vec<(String, HskLevels)>>,
+}
+
+impl HskDictionary {
+    /// Returns the level of the word. Like for TOCFL, the pinyin is only ignored if the word
+    /// has only one reading in CEDICT.
+    pub fn get_level(
+        &self,
+        simplified: &str,
+        pinyin_ws_tone_number: &str,
+        is_unambiguous: bool,
+    ) -> Option<HskLevels> {
+        let words = self.words.get(simplified)?;
+        if is_unambiguous {
+            return words.iter().map(|(_, level)| *level).min();
+        }
+        let pinyin = normalize_pinyin(pinyin_ws_tone_number);
+        words
+            .iter()
+            .filter(|(word_pinyin, _)| *word_pinyin == pinyin)
+            .map(|(_, level)| *level)
+            .min()
+    }
+}
+
+pub fn load_hsk_dictionary(path: &str) -> HskDictionary {
+    let mut dict = HskDictionary::default();
+    if !std::path::Path::new(path).exists() {
+        println!("Skipping missing HSK list {}", path);
+        return dict;
+    }
+    let file = std::fs::read_to_string(path).unwrap();
+    let mut rdr = csv::Reader::from_reader(file.as_bytes());
+    for result in rdr.deserialize() {
+        let row: HskRow = result.unwrap();
+        let Some(level) = parse_level(&row.level) else {
+            println!("Skipping HSK row with invalid level {:?}", row);
+            continue;
+        };
+        dict.words
+            .entry(row.simplified.trim().to_string())
+            .or_default()
+            .push((normalize_pinyin(&row.pinyin), level));
+    }
+    dict
+}
+
+/// Parses the level, e.g. "3", "HSK3" or the band "7-9"
+fn parse_level(text: &str) -> Option<HskLevels> {
+    let text = text.trim().trim_start_matches("HSK");
+    let (first, last) = text.split_once('-').unwrap_or((text, text));
+    let first = first.trim().parse().ok()?;
+    let last = last.trim().parse().ok()?;
+    (first <= last).then_some(HskLevels { first, last })
+}
+
+/// Pinyin with tone marks, lowercase and without whitespace, e.g. "Ai4 hao4" to "àihào"
+fn normalize_pinyin(pinyin: &str) -> String {
+    prettify(pinyin.to_lowercase())
+        .chars()
+        .filter(|cha| !cha.is_whitespace())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("3"), Some(HskLevels { first: 3, last: 3 }));
+        assert_eq!(parse_level("7-9"), Some(HskLevels { first: 7, last: 9 }));
+        assert_eq!(parse_level("HSK2"), Some(HskLevels { first: 2, last: 2 }));
+        assert_eq!(parse_level("9-7"), None);
+    }
+
+    #[test]
+    fn test_normalize_pinyin() {
+        assert_eq!(normalize_pinyin("ai4 hao4"), "àihào");
+        assert_eq!(normalize_pinyin("àihào"), "àihào");
+    }
+}
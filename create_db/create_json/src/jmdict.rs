@@ -1,4 +1,7 @@
-use std::io::{BufRead, BufReader};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -38,8 +41,87 @@ pub struct JapaneseWord {
     kanji: Vec<Kanji>,
     kana: Vec<Kana>,
     meanings: Meanings,
-    #[serde(default)]
-    useKana: bool,
+    #[serde(default, rename = "useKana")]
+    use_kana: bool,
+}
+
+/// A JMdict word with the same kanji spelling as a chinese entry. Used to show the japanese
+/// meaning, which may differ from the chinese one (e.g. 勉強 or 手紙).
+#[derive(Debug, Clone, Serialize)]
+pub struct JapaneseEntry {
+    kanji: String,
+    readings: Vec<String>,
+    meanings: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meanings_de: Vec<String>,
+    common: bool,
+}
+
+/// Max number of meanings per language stored for a japanese word
+const MAX_MEANINGS: usize = 3;
+
+/// Lookup of JMdict words by their kanji spelling
+pub struct JmdictIndex {
+    words: Vec<JapaneseWord>,
+    // Kanji -> index into words
+    by_kanji: HashMap<String, Vec<usize>>,
+}
+
+impl JmdictIndex {
+    pub fn new(words: Vec<JapaneseWord>) -> Self {
+        let mut by_kanji: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, word) in words.iter().enumerate() {
+            for kanji in &word.kanji {
+                by_kanji
+                    .entry(kanji.text.to_string())
+                    .or_default()
+                    .push(index);
+            }
+        }
+        Self { words, by_kanji }
+    }
+
+    /// Returns the JMdict words with exactly this kanji spelling, common words first
+    pub fn get(&self, kanji: &str) -> Vec<JapaneseEntry> {
+        let mut entries: Vec<JapaneseEntry> = self
+            .by_kanji
+            .get(kanji)
+            .into_iter()
+            .flatten()
+            .map(|index| {
+                let word = &self.words[*index];
+                let kanji_element = word.kanji.iter().find(|el| el.text == kanji);
+                // Kana readings restricted to this spelling, or all readings of the word
+                let readings = match kanji_element {
+                    Some(el) if !el.readings.is_empty() => el.readings.clone(),
+                    _ => word.kana.iter().map(|kana| kana.text.to_string()).collect(),
+                };
+                let meanings_de = word
+                    .meanings
+                    .ger
+                    .iter()
+                    .flatten()
+                    .take(MAX_MEANINGS)
+                    .map(|german| german.text.to_string())
+                    .collect();
+                JapaneseEntry {
+                    kanji: kanji.to_string(),
+                    readings,
+                    meanings: word
+                        .meanings
+                        .eng
+                        .iter()
+                        .take(MAX_MEANINGS)
+                        .cloned()
+                        .collect(),
+                    meanings_de,
+                    common: word.commonness.unwrap_or(0) > 0,
+                }
+            })
+            .collect();
+        entries.sort_by_key(|entry| !entry.common);
+        entries
+    }
 }
 
 pub fn load_jmdict(path: &str) -> Vec<JapaneseWord> {
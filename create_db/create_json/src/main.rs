@@ -1,6 +1,20 @@
+mod cccc;
+mod chengyu;
+mod hsk;
 mod jmdict;
-
-use std::{collections::HashMap, fs, io::Write};
+// The kana conversion of the webpage, so the readings in `kana_search` match the converted queries
+#[allow(dead_code)]
+#[path = "../../../webpage/src/kana.rs"]
+mod kana;
+mod naer;
+mod syllable;
+mod translations;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+};
 
 use pinyin_zhuyin::pinyin_to_zhuyin;
 use prettify_pinyin::prettify;
@@ -9,14 +23,24 @@ use serde::{de, Deserialize, Serialize};
 use serde_json::Number;
 use tocfl::{load_tocfl_dictionary, TOCFLDictionary};
 
-use crate::jmdict::load_jmdict;
+use crate::cccc::{load_cccc_dictionary, CcccDictionary};
+use crate::chengyu::{is_chengyu, load_chengyu_list, split_literal_figurative, CHENGYU_PATH};
+use crate::hsk::{load_hsk_dictionary, HskDictionary, HSK2_PATH, HSK3_PATH};
+use crate::jmdict::{load_jmdict, JapaneseEntry, JmdictIndex};
+use crate::naer::{load_naer_dictionary, NaerDictionary};
+use crate::syllable::{split_syllables, tone_pattern, Syllable};
+use crate::translations::{get_translation_dicts, Example, TranslationDict};
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct FreqRow {
     text: String,
-    count: u64, // count on its own
+    count: u64,             // count on its own
     count_per_million: f64, // count
-                //log_count: f64,
+    //log_count: f64,
+    // Contextual diversity, percentage of films the text occurs in. Missing in the word list
+    // built from SUBTLEX-CH-WF_PoS.
+    #[serde(default)]
+    cd_percentage: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -26,6 +50,7 @@ struct FreqEntry {
     count_per_million_self: f64,      // count_per_million on its own
     count_in_others: u64,             // how often does this part occur in others
     count_per_million_in_others: f64, // how often does this part occur in others
+    cd_percentage: Option<f64>,       // contextual diversity
 }
 impl From<FreqRow> for FreqEntry {
     fn from(value: FreqRow) -> Self {
@@ -35,6 +60,7 @@ impl From<FreqRow> for FreqEntry {
             count_per_million_self: value.count_per_million,
             count_per_million_in_others: 0.0,
             count_in_others: 0,
+            cd_percentage: value.cd_percentage,
         }
     }
 }
@@ -52,9 +78,7 @@ fn get_commonness() -> Commonnness {
     // e.g. 午 on its own is uncommon, but 下午 [xiawu] is quite common
     for (word, v) in word_freq.iter_mut() {
         for cha in word.chars() {
-            let entry = char_freq
-                .entry(cha.to_string())
-                .or_insert_with(Default::default);
+            let entry = char_freq.entry(cha.to_string()).or_default();
             entry.count_in_others += v.count_self;
             entry.count_per_million_in_others += v.count_per_million_self;
         }
@@ -67,43 +91,13 @@ fn get_commonness() -> Commonnness {
 }
 
 impl Commonnness {
-    fn get_freq<'a>(
-        &'a self,
-        trad: &'a str,
-        simpl: &'a str,
-    ) -> impl Iterator<Item = &FreqEntry> + 'a {
-        [
-            self.word_freq.get(simpl),
-            self.word_freq.get(trad),
-            self.char_freq.get(simpl),
-            self.char_freq.get(trad),
-        ]
-        .into_iter()
-        .flatten()
-    }
-
-    fn get_count_per_million_self(&self, trad: &str, simpl: &str) -> f64 {
-        self.get_freq(trad, simpl)
-            .map(|el| el.count_per_million_self)
-            .sum()
-    }
-
-    fn get_count_per_million_in_others(&self, trad: &str, simpl: &str) -> f64 {
-        self.get_freq(trad, simpl)
-            .map(|el| el.count_per_million_in_others)
-            .sum()
-    }
-
-    fn get_count_self(&self, trad: &str, simpl: &str) -> u64 {
-        self.get_freq(trad, simpl)
-            .map(|el| el.count_self)
-            .sum::<u64>()
+    /// SUBTLEX is based on simplified chinese subtitles
+    fn get_word_freq(&self, simplified: &str) -> Option<&FreqEntry> {
+        self.word_freq.get(simplified)
     }
 
-    fn get_count_in_others(&self, trad: &str, simpl: &str) -> u64 {
-        self.get_freq(trad, simpl)
-            .map(|el| el.count_in_others)
-            .sum::<u64>()
+    fn get_char_freq(&self, simplified: &str) -> Option<&FreqEntry> {
+        self.char_freq.get(simplified)
     }
 }
 
@@ -111,6 +105,10 @@ impl Commonnness {
 /// This is of limited usage since we need the pinyin to differentiate between different meanings
 /// (some of which are less commonn) of one Hanzi
 fn parse_commonness(path: &str) -> HashMap<String, FreqEntry> {
+    if !std::path::Path::new(path).exists() {
+        println!("Skipping missing frequency list {}, run ch_freq", path);
+        return HashMap::new();
+    }
     let data = std::fs::read_to_string(path).unwrap();
     data.lines()
         .map(|line| {
@@ -124,6 +122,61 @@ fn parse_commonness(path: &str) -> HashMap<String, FreqEntry> {
         .collect()
 }
 
+/// Part-of-speech distribution of a word, e.g. 学习: verb 0.92, noun 0.08
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PosShare {
+    pos: String,
+    share: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct WordPos {
+    text: String,
+    pos: Vec<PosShare>,
+}
+
+/// Part of speech by simplified word, from SUBTLEX-CH-WF_PoS converted by `ch_freq`
+fn get_word_pos(path: &str) -> HashMap<String, Vec<PosShare>> {
+    if !std::path::Path::new(path).exists() {
+        println!("Skipping missing part-of-speech list {}, run ch_freq", path);
+        return HashMap::new();
+    }
+    let data = std::fs::read_to_string(path).unwrap();
+    data.lines()
+        .map(|line| {
+            let word_pos: WordPos = serde_json::from_str(line).unwrap();
+            (word_pos.text, word_pos.pos)
+        })
+        .collect()
+}
+
+/// Character frequency in classical chinese texts, see `ch_freq`
+#[derive(Deserialize, Debug)]
+struct ClassicalFreqRow {
+    text: String,
+    count_per_million: f64,
+    // Readings with tone numbers, e.g. ["bu4", "bu2"]
+    pinyin: Vec<String>,
+}
+
+/// Classical character frequency by simplified character
+fn get_classical_char_freq(path: &str) -> HashMap<String, ClassicalFreqRow> {
+    if !std::path::Path::new(path).exists() {
+        println!(
+            "Skipping missing classical frequency list {}, run ch_freq",
+            path
+        );
+        return HashMap::new();
+    }
+    let data = std::fs::read_to_string(path).unwrap();
+    data.lines()
+        .map(|line| {
+            let row: ClassicalFreqRow = serde_json::from_str(line).unwrap();
+            (row.text.to_string(), row)
+        })
+        .collect()
+}
+
 #[derive(Debug, serde::Deserialize)]
 #[allow(dead_code)]
 struct TocflEntry {
@@ -158,6 +211,46 @@ fn get_tocfl_levels() -> HashMap<(String, String), TocflEntry> {
     tocfl_map
 }
 
+// Source: https://www.hackingchinese.com/ (high-frequency words missing in the TOCFL lists)
+// Key is the traditional word, value the TOCFL level of the column it is listed in
+fn get_hacking_chinese_words() -> HashMap<String, u32> {
+    let mut words = HashMap::new();
+    let file = std::fs::read_to_string("./hacking-chinese_missing-tocfl-words.csv")
+        .expect("could not read hacking-chinese_missing-tocfl-words.csv");
+
+    let mut rdr = csv::Reader::from_reader(file.as_bytes());
+    // Columns are "TOCFL1" to "TOCFL5"
+    let levels: Vec<u32> = rdr
+        .headers()
+        .unwrap()
+        .iter()
+        .map(|header| header.trim_start_matches("TOCFL").parse().unwrap())
+        .collect();
+    for result in rdr.records() {
+        let record = result.unwrap();
+        for (word, level) in record.iter().zip(levels.iter()) {
+            if !word.trim().is_empty() {
+                words.insert(word.trim().to_string(), *level);
+            }
+        }
+    }
+
+    words
+}
+
+/// Separable verbs (離合詞) with their split point, e.g. 睡|覺 -> ("睡覺", 1)
+fn get_separable_verbs() -> HashMap<String, usize> {
+    let file = std::fs::read_to_string("./separable_verbs.txt").unwrap();
+    file.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (first, second) = line.split_once('|')?;
+            Some((format!("{}{}", first, second), first.chars().count()))
+        })
+        .collect()
+}
+
 #[derive(Debug, Default)]
 struct Radicals {
     traditional_to_radicals: HashMap<String, Vec<Vec<String>>>,
@@ -238,45 +331,34 @@ fn normalize_definitions_and_extract_taiwan_pinyin(
 }
 use tocfl::Entry as TOCFLEntry;
 
-fn get_de_dict() -> HashMap<(String, String), Vec<String>> {
-    let mut dict = HashMap::new();
-    let all = std::fs::read_to_string("../handedict.u8").unwrap();
-    for line in all.lines() {
-        let parsed = cedict::parse_line(line);
-        let e = match parsed {
-            cedict::Line::Entry(e) => e,
-            cedict::Line::Comment(_) | cedict::Line::Metadata(_, _) | cedict::Line::Empty => {
-                continue;
-            }
-            cedict::Line::Incorrect => {
-                continue;
-            }
-        };
-        let pinyin_ws_tone_number = e.pinyin().to_string();
-        let traditional = e.traditional().to_string();
-        let definitions = e.definitions().map(ToString::to_string).collect::<Vec<_>>();
-        if definitions
-            .iter()
-            .any(|def| def.contains(&"我家有四口人".to_string()))
-        {
-            dbg!(&definitions);
-        }
-        dict.insert((traditional, pinyin_ws_tone_number), definitions);
-    }
-    dict
-}
 fn main() {
-    //let jmdict = load_jmdict("../../../japanese-dictionary/jmdict.json");
+    let jmdict_path = "../../../japanese-dictionary/jmdict.json";
+    let jmdict = if std::path::Path::new(jmdict_path).exists() {
+        load_jmdict(jmdict_path)
+    } else {
+        println!("Skipping missing JMdict {}", jmdict_path);
+        Vec::new()
+    };
+    let jmdict = JmdictIndex::new(jmdict);
 
     let kanji_dict: KanjiDict =
         serde_json::from_str(&fs::read_to_string("./kanji.json").unwrap()).unwrap();
 
     let tocfl_dict = load_tocfl_dictionary();
     let common_char = tocfl::compile_common_chars();
-
-    let de_dict = get_de_dict();
-
-    //let commonness = get_commonness();
+    let cccc_dict = load_cccc_dictionary();
+    let naer_dict = load_naer_dictionary();
+    let hacking_chinese_words = get_hacking_chinese_words();
+    let hsk3_dict = load_hsk_dictionary(HSK3_PATH);
+    let chengyu_list = load_chengyu_list(CHENGYU_PATH);
+    let separable_verbs = get_separable_verbs();
+    let hsk2_dict = load_hsk_dictionary(HSK2_PATH);
+
+    let mut translation_dicts = get_translation_dicts();
+
+    let commonness = get_commonness();
+    let word_pos = get_word_pos("../ch_freq/word_pos.json");
+    let classical_char_freq = get_classical_char_freq("../ch_freq/classical_char_freq.json");
     let radicals = get_character_radicals();
 
     let mut entries = Vec::new();
@@ -294,71 +376,90 @@ fn main() {
             }
         };
 
-        let mut definitions = e.definitions().map(ToString::to_string).collect::<Vec<_>>();
-        let pinyin_taiwan = normalize_definitions_and_extract_taiwan_pinyin(&mut definitions);
-
-        let pinyin_ws_tone_number = e.pinyin().to_string();
-        let pinyin_pretty = prettify(e.pinyin().to_string());
-
-        let meanings_de = de_dict
-            .get(&(e.traditional().to_string(), pinyin_ws_tone_number.clone()))
-            .cloned()
-            .unwrap_or_default();
+        let definitions = e.definitions().map(ToString::to_string).collect::<Vec<_>>();
+        let mut entry = create_entry(
+            e.simplified(),
+            e.traditional(),
+            e.pinyin(),
+            definitions,
+            &kanji_dict,
+            &radicals,
+        );
+        add_translations(&mut entry, &mut translation_dicts);
+        entries.push(entry);
+    }
 
-        let zhuyin = pinyin_pretty
-            .split_whitespace()
-            .map(|pinyin_component| {
-                if let Some(zhuyin) = pinyin_to_zhuyin(pinyin_component) {
-                    zhuyin
-                } else {
-                    pinyin_component.to_string()
+    // Add entries for the headwords which are only in the translation dictionaries
+    for dict in &translation_dicts {
+        if dict.merge_missing {
+            for translation in dict.unmatched_entries() {
+                let mut entry = create_entry(
+                    &translation.simplified,
+                    &translation.traditional,
+                    &translation.pinyin,
+                    Vec::new(),
+                    &kanji_dict,
+                    &radicals,
+                );
+                for other_dict in translation_dicts.iter().filter(|other| !other.is_empty()) {
+                    entry
+                        .translations
+                        .insert(format!("meanings_{}", other_dict.lang), Vec::new());
                 }
-            })
-            .collect::<Vec<String>>()
-            .join(" ");
+                entry.translations.insert(
+                    format!("meanings_{}", dict.lang),
+                    translation.meanings.clone(),
+                );
+                entry.examples = translation.examples.clone();
+                entries.push(entry);
+            }
+        }
+        dict.print_report();
+    }
 
-        let simplified = e.simplified().to_string();
-        let traditional = e.traditional().to_string();
+    // Link to the japanese words with the same kanji spelling
+    for entry in &mut entries {
+        let kanji = kanji_hanzi_converter::convert_to_japanese_kanji(&entry.traditional);
+        entry.japanese = jmdict.get(&kanji);
+    }
 
-        let kanji_char = kanji_hanzi_converter::convert_to_japanese_kanji(e.traditional());
-        let kanji = kanji_dict.get(kanji_char.as_str()).cloned();
+    // Add the japanese readings of the kanji for search, e.g. がく for 學
+    for entry in &mut entries {
+        if let Some(kanji) = entry.kanji.as_ref() {
+            entry.kana_search = kana_readings(kanji);
+        }
+    }
 
-        let simplified = simplified.to_string();
-        let traditional = traditional.to_string();
+    // Add the part of speech, and tag the main word classes, e.g. #verb
+    for entry in &mut entries {
+        if let Some(pos) = word_pos.get(&entry.simplified) {
+            entry.pos = pos.clone();
+            for pos in pos.iter().filter(|pos| pos.share >= 0.2) {
+                entry.tags.push(format!("#{}", pos.pos));
+            }
+        }
+    }
 
-        let simplified_radicals = radicals
-            .simplified_to_radicals
-            .get(&simplified)
-            .map(ToOwned::to_owned)
-            .unwrap_or_default();
-        let traditional_radicals = radicals
-            .traditional_to_radicals
-            .get(&traditional)
-            .map(ToOwned::to_owned)
-            .unwrap_or_default();
+    // Tag four-character idioms, and split their literal and figurative meanings
+    for entry in &mut entries {
+        if is_chengyu(
+            &entry.traditional,
+            &entry.simplified,
+            &entry.meanings,
+            &chengyu_list,
+        ) {
+            entry.tags.push("#chengyu".to_string());
+            (entry.literal_meanings, entry.figurative_meanings) =
+                split_literal_figurative(&entry.meanings);
+        }
+    }
 
-        let entry = Entry {
-            simplified_radicals,
-            traditional_radicals,
-            simplified,
-            traditional: traditional.to_string(),
-            pinyin: e.pinyin().to_string(),
-            pinyin_taiwan,
-            pinyin_search: Vec::new(),
-            zhuyin,
-            pinyin_pretty,
-            tocfl_level: None,
-            meanings: definitions,
-            meanings_de,
-            commonness_boost: 0.0,
-            count_per_million_written: 0,
-            count_per_million_spoken: 0,
-            count_per_million_in_others: 0,
-            pinyin_ws_tone_number,
-            tags: Vec::new(),
-            kanji,
-        };
-        entries.push(entry);
+    // Mark separable verbs, e.g. 睡覺 is also used as 睡了一個覺
+    for entry in &mut entries {
+        if let Some(split) = separable_verbs.get(&entry.traditional) {
+            entry.separable_split = Some(*split);
+            entry.tags.push("#separable".to_string());
+        }
     }
 
     // Add pinyin variants for search (this could be done by a tokenizer)
@@ -389,13 +490,12 @@ fn main() {
         entry.pinyin_search = filter_duplicates(pinyin_search);
     }
 
-    // Add WK tags
+    // Add WK tags. Words get the maximum level of their characters, so they are readable by a
+    // WaniKani user with that level.
     for entry in &mut entries {
-        if let Some(kanji) = entry.kanji.as_ref() {
-            if let Some(level) = kanji.wk_level {
-                entry.tags.push("#WK".to_string());
-                entry.tags.push(format!("#WaniKaniLevel{}", level));
-            }
+        if let Some(level) = wk_level(entry) {
+            entry.tags.push("#WK".to_string());
+            entry.tags.push(format!("#WaniKaniLevel{}", level));
             // Filter duplicates
             entry.tags = filter_duplicates(entry.tags.clone());
         }
@@ -434,10 +534,30 @@ fn main() {
         *count += 1;
     }
 
+    let mut simplified_count: HashMap<String, u32> = HashMap::new();
+    for entry in &entries {
+        *simplified_count
+            .entry(entry.simplified.clone())
+            .or_default() += 1;
+    }
+
     for entry in &mut entries {
         let is_unambiguous = kanji_count[&entry.traditional] == 1;
         resolve_tocfl_commonness(entry, &tocfl_dict, &common_char, is_unambiguous);
+        resolve_cccc_level(entry, &cccc_dict, is_unambiguous);
+        resolve_naer_level(entry, &naer_dict, is_unambiguous);
+        let is_unambiguous_simplified = simplified_count[&entry.simplified] == 1;
+        resolve_hsk_levels(entry, &hsk3_dict, &hsk2_dict, is_unambiguous_simplified);
+        resolve_subtlex_frequency(entry, &commonness, simplified_count[&entry.simplified]);
+        entry.commonness_boost = commonness_boost(entry);
+        entry.zipf = zipf(entry, &commonness, simplified_count[&entry.simplified]);
     }
+    resolve_hacking_chinese_words(&mut entries, &hacking_chinese_words);
+    for entry in &mut entries {
+        let is_unambiguous = kanji_count[&entry.traditional] == 1;
+        resolve_classical_frequency(entry, &classical_char_freq, &commonness, is_unambiguous);
+    }
+    resolve_components(&mut entries);
     for entry in entries {
         out.write_all(serde_json::to_string(&entry).unwrap().as_bytes())
             .unwrap();
@@ -446,6 +566,250 @@ fn main() {
     println!("Hello, world!");
 }
 
+fn create_entry(
+    simplified: &str,
+    traditional: &str,
+    pinyin: &str,
+    mut definitions: Vec<String>,
+    kanji_dict: &KanjiDict,
+    radicals: &Radicals,
+) -> Entry {
+    let pinyin_taiwan = normalize_definitions_and_extract_taiwan_pinyin(&mut definitions);
+
+    let pinyin_ws_tone_number = pinyin.to_string();
+    let pinyin_compact = pinyin.to_lowercase().remove_whitespace();
+    let pinyin_toneless = pinyin_compact.clone().remove_numbers();
+    let syllables = split_syllables(pinyin).unwrap_or_default();
+    let tone_pattern = (!syllables.is_empty()).then(|| tone_pattern(&syllables));
+    let rhyme = syllables.last().map(|syllable| syllable.final_.clone());
+    let pinyin_pretty = prettify(pinyin.to_string());
+
+    let zhuyin = pinyin_pretty
+        .split_whitespace()
+        .map(|pinyin_component| {
+            if let Some(zhuyin) = pinyin_to_zhuyin(pinyin_component) {
+                zhuyin
+            } else {
+                pinyin_component.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let kanji_char = kanji_hanzi_converter::convert_to_japanese_kanji(traditional);
+    let kanji = kanji_dict.get(kanji_char.as_str()).cloned();
+
+    // Kanji data of the single characters of words
+    let kanji_chars = if traditional.chars().count() > 1 {
+        traditional
+            .chars()
+            .filter_map(|cha| {
+                let kanji_char = kanji_hanzi_converter::convert_to_japanese_kanji(&cha.to_string());
+                let kanji = kanji_dict.get(kanji_char.as_str())?;
+                Some(KanjiCharInfo::new(cha, kanji))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let simplified = simplified.to_string();
+    let traditional = traditional.to_string();
+
+    let simplified_radicals = radicals
+        .simplified_to_radicals
+        .get(&simplified)
+        .map(ToOwned::to_owned)
+        .unwrap_or_default();
+    let traditional_radicals = radicals
+        .traditional_to_radicals
+        .get(&traditional)
+        .map(ToOwned::to_owned)
+        .unwrap_or_default();
+
+    Entry {
+        simplified_radicals,
+        traditional_radicals,
+        simplified,
+        traditional: traditional.to_string(),
+        pinyin: pinyin.to_string(),
+        pinyin_taiwan,
+        pinyin_search: Vec::new(),
+        zhuyin,
+        pinyin_pretty,
+        tocfl_level: None,
+        cccc_level: None,
+        naer_level: None,
+        naer_char_level: None,
+        hacking_chinese_level: None,
+        hsk_level: None,
+        hsk2_level: None,
+        pos: Vec::new(),
+        meanings: definitions,
+        separable_split: None,
+        literal_meanings: Vec::new(),
+        figurative_meanings: Vec::new(),
+        translations: BTreeMap::new(),
+        examples: Vec::new(),
+        commonness_boost: 0.0,
+        count_per_million_written: 0,
+        count_per_million_spoken: 0,
+        count_per_million_in_others: 0,
+        count_per_million_estimated: 0,
+        subtlex_per_million: 0.0,
+        classical_per_million: 0.0,
+        classical_boost: 1.0,
+        subtlex_cd_percentage: None,
+        zipf: 0.0,
+        pinyin_ws_tone_number,
+        pinyin_compact,
+        pinyin_toneless,
+        syllables,
+        tone_pattern,
+        rhyme,
+        tags: Vec::new(),
+        kanji,
+        components: Vec::new(),
+        kanji_chars,
+        kana_search: Vec::new(),
+        japanese: Vec::new(),
+    }
+}
+
+/// Adds the `meanings_{lang}` and examples of the translation dictionaries
+fn add_translations(entry: &mut Entry, translation_dicts: &mut [TranslationDict]) {
+    for dict in translation_dicts.iter_mut() {
+        if dict.is_empty() {
+            continue;
+        }
+        let translation = dict.get(&entry.traditional, &entry.pinyin_ws_tone_number);
+        let meanings = translation
+            .map(|translation| translation.meanings.clone())
+            .unwrap_or_default();
+        if let Some(translation) = translation {
+            entry.examples.extend(translation.examples.iter().cloned());
+        }
+        entry
+            .translations
+            .insert(format!("meanings_{}", dict.lang), meanings);
+    }
+}
+
+/// SUBTLEX provides no pinyin, so for ambiguous words the frequency is split between the
+/// readings, unless TOCFL lists the reading.
+fn resolve_subtlex_frequency(entry: &mut Entry, commonness: &Commonnness, num_readings: u32) {
+    let Some(freq) = commonness.get_word_freq(&entry.simplified) else {
+        return;
+    };
+    let is_tocfl_reading = entry.count_per_million_spoken + entry.count_per_million_written > 0;
+    let share = if num_readings <= 1 || is_tocfl_reading {
+        1.0
+    } else {
+        1.0 / num_readings as f64
+    };
+    entry.subtlex_per_million = freq.count_per_million_self * share;
+    entry.subtlex_cd_percentage = freq.cd_percentage;
+}
+
+/// Size of the SUBTLEX-CH corpus in million words
+const SUBTLEX_CORPUS_MILLIONS: f64 = 33.5;
+
+/// Frequency on the Zipf scale (van Heuven et al. 2014), log10 of the frequency per billion
+/// words. 1-3 are rare words, 4-7 common words.
+///
+/// The frequency per million is the mean of the sources with a count for the word: TOCFL written,
+/// TOCFL spoken and SUBTLEX-CH. Sources without a count are left out, so words only SUBTLEX-CH
+/// knows are not pulled down by TOCFL. Single characters without a word count fall back to the
+/// SUBTLEX-CH character count, split between the readings like the word count.
+///
+/// Smoothing: one occurrence in SUBTLEX-CH is added (Laplace), so words missing in all sources
+/// get the lowest value of log10(1 / 33.5) + 3 ≈ 1.5 instead of minus infinity.
+fn zipf(entry: &Entry, commonness: &Commonnness, num_readings: u32) -> f64 {
+    let mut sources: Vec<f64> = [
+        entry.count_per_million_written as f64,
+        entry.count_per_million_spoken as f64,
+        entry.subtlex_per_million,
+    ]
+    .into_iter()
+    .filter(|per_million| *per_million > 0.0)
+    .collect();
+    if sources.is_empty() && entry.simplified.chars().count() == 1 && !is_variant_entry(entry) {
+        if let Some(freq) = commonness.get_char_freq(&entry.simplified) {
+            sources.push(freq.count_per_million_self / num_readings.max(1) as f64);
+        }
+    }
+    let per_million = if sources.is_empty() {
+        0.0
+    } else {
+        sources.iter().sum::<f64>() / sources.len() as f64
+    };
+    let zipf = (per_million + 1.0 / SUBTLEX_CORPUS_MILLIONS).log10() + 3.0;
+    (zipf * 100.0).round() / 100.0
+}
+
+/// Sets the classical frequency of single characters. Characters with multiple readings get
+/// the frequency for the readings listed in the classical list.
+///
+/// Characters which are at least 5 times more frequent in classical texts than in SUBTLEX-CH
+/// and common in classical texts (100 per million) are tagged `#classical`, e.g. 之, 曰 and 矣.
+fn resolve_classical_frequency(
+    entry: &mut Entry,
+    classical_char_freq: &HashMap<String, ClassicalFreqRow>,
+    commonness: &Commonnness,
+    is_unambiguous: bool,
+) {
+    if entry.simplified.chars().count() != 1 || is_variant_entry(entry) {
+        return;
+    }
+    let Some(freq) = classical_char_freq.get(&entry.simplified) else {
+        return;
+    };
+    let pinyin = entry.pinyin_ws_tone_number.to_lowercase();
+    if !is_unambiguous && !freq.pinyin.is_empty() && !freq.pinyin.contains(&pinyin) {
+        return;
+    }
+    entry.classical_per_million = freq.count_per_million;
+    entry.classical_boost = freq.count_per_million.sqrt().max(4.0) / 4.0;
+
+    let subtlex_per_million = commonness
+        .get_char_freq(&entry.simplified)
+        .map(|freq| freq.count_per_million_self)
+        .unwrap_or_default();
+    if freq.count_per_million >= 100.0 && freq.count_per_million >= 5.0 * subtlex_per_million {
+        entry.tags.push("#classical".to_string());
+    }
+}
+
+fn is_variant_entry(entry: &Entry) -> bool {
+    !entry.meanings.is_empty() && entry.meanings.iter().all(|def| def.contains("variant"))
+}
+
+/// Combines the TOCFL counts with the SUBTLEX word frequency, so words missing in the TOCFL
+/// lists are still ranked by their frequency.
+fn commonness_boost(entry: &Entry) -> f64 {
+    if is_variant_entry(entry) {
+        return 1.0;
+    }
+    let tocfl = entry.count_per_million_spoken as f64
+        + entry.count_per_million_written as f64
+        + entry.count_per_million_in_others as f64
+        + entry.count_per_million_estimated as f64;
+    // Words which occur only in a few films (e.g. the name of a main character) are weighted down,
+    // if the contextual diversity is known
+    let cd_weight = entry
+        .subtlex_cd_percentage
+        .map_or(1.0, |cd_percentage| (cd_percentage / 100.0).sqrt());
+    let subtlex = entry.subtlex_per_million * cd_weight;
+
+    let boost = (tocfl + subtlex).sqrt().max(4.0) / 4.0;
+    // Rare words are below the floor of the formula above, so they are ranked by their
+    // SUBTLEX frequency only.
+    let rare_word_boost = (1.0 + subtlex).log10() / 10.0;
+    let boost = boost + rare_word_boost;
+    assert!(!boost.is_nan());
+    boost
+}
+
 fn resolve_tocfl_commonness(
     entry: &mut Entry,
     tocfl_dict: &TOCFLDictionary<TOCFLEntry>,
@@ -485,17 +849,8 @@ fn resolve_tocfl_commonness(
         dbg!(&entry.pinyin_ws_tone_number);
         //dbg!(e.clone());
     }
-    entry.commonness_boost = (count_per_million_spoken as f64
-        + count_per_million_written as f64
-        + count_per_million_in_others as f64)
-        .sqrt()
-        .max(4.0)
-        / 4.0;
-    assert!(!entry.commonness_boost.is_nan());
-    let is_variant_entry = entry.meanings.iter().all(|def| def.contains("variant"));
-    if is_variant_entry {
+    if is_variant_entry(entry) {
         count_per_million_written = 0;
-        entry.commonness_boost = 1.0;
     }
     if count_per_million_written > 150 {
         // top 1000
@@ -526,6 +881,154 @@ fn resolve_tocfl_commonness(
     }
 }
 
+fn resolve_cccc_level(entry: &mut Entry, cccc_dict: &CcccDictionary, is_unambiguous: bool) {
+    entry.cccc_level = cccc_dict.get_level(
+        &entry.traditional,
+        &entry.pinyin_ws_tone_number,
+        is_unambiguous,
+    );
+    if let Some(level) = entry.cccc_level {
+        entry.tags.push("#CCCC".to_string());
+        entry.tags.push(format!("#CCCC{}", level));
+    }
+}
+
+/// HSK lists are in simplified chinese, so the ambiguity is resolved on the simplified word
+fn resolve_hsk_levels(
+    entry: &mut Entry,
+    hsk3_dict: &HskDictionary,
+    hsk2_dict: &HskDictionary,
+    is_unambiguous: bool,
+) {
+    let hsk_levels = hsk3_dict.get_level(
+        &entry.simplified,
+        &entry.pinyin_ws_tone_number,
+        is_unambiguous,
+    );
+    entry.hsk_level = hsk_levels.map(|levels| levels.first);
+    if let Some(levels) = hsk_levels {
+        entry.tags.push("#HSK".to_string());
+        // The band 7-9 gets #HSK7, #HSK8 and #HSK9
+        for level in levels.first..=levels.last {
+            entry.tags.push(format!("#HSK{}", level));
+        }
+    }
+    entry.hsk2_level = hsk2_dict
+        .get_level(
+            &entry.simplified,
+            &entry.pinyin_ws_tone_number,
+            is_unambiguous,
+        )
+        .map(|levels| levels.first);
+    if let Some(level) = entry.hsk2_level {
+        entry.tags.push(format!("#HSKLegacy{}", level));
+    }
+}
+
+fn resolve_naer_level(entry: &mut Entry, naer_dict: &NaerDictionary, is_unambiguous: bool) {
+    if let Some(level) = naer_dict.get_word_level(
+        &entry.traditional,
+        &entry.pinyin_ws_tone_number,
+        is_unambiguous,
+    ) {
+        entry.naer_level = Some(level.level);
+        entry.tags.push("#NAER".to_string());
+        entry.tags.push(format!("#NAER{}", level.band.name()));
+        entry.tags.push(format!("#NAER{}", level.level.floor()));
+    }
+    if let Some(level) = naer_dict.get_char_level(&entry.traditional) {
+        entry.naer_char_level = Some(level.level);
+        if entry.traditional.chars().count() == 1 {
+            entry.tags.push(format!("#NAERChar{}", level.level.floor()));
+        }
+    }
+}
+
+/// The Hacking Chinese list has no pinyin, so only the most common reading of a word is tagged,
+/// e.g. 了 le5 but not liao3.
+///
+/// Like the TOCFL counts, the words get a count for the commonness boost: the median count of
+/// the TOCFL words with their suggested level.
+fn resolve_hacking_chinese_words(entries: &mut [Entry], words: &HashMap<String, u32>) {
+    let level_counts = median_tocfl_counts(entries);
+    let mut most_common: HashMap<&str, usize> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if !words.contains_key(&entry.traditional) || is_variant_entry(entry) {
+            continue;
+        }
+        let best = most_common.entry(&entry.traditional).or_insert(index);
+        if entries[*best].commonness_boost < entry.commonness_boost {
+            *best = index;
+        }
+    }
+    let indices: Vec<usize> = most_common.into_values().collect();
+    for index in indices {
+        let entry = &mut entries[index];
+        entry.hacking_chinese_level = words.get(&entry.traditional).copied();
+        entry.tags.push("#HackingChineseEssential".to_string());
+        if entry.tocfl_level.is_none() {
+            entry.count_per_million_estimated = entry
+                .hacking_chinese_level
+                .and_then(|level| level_counts.get(&level).copied())
+                .unwrap_or(0);
+            entry.commonness_boost = commonness_boost(entry);
+        }
+    }
+}
+
+/// Median of the written and spoken TOCFL counts per TOCFL level
+fn median_tocfl_counts(entries: &[Entry]) -> HashMap<u32, u64> {
+    let mut counts: HashMap<u32, Vec<u64>> = HashMap::new();
+    for entry in entries {
+        if let Some(level) = entry.tocfl_level {
+            counts
+                .entry(level)
+                .or_default()
+                .push(entry.count_per_million_written + entry.count_per_million_spoken);
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(level, mut counts)| {
+            counts.sort_unstable();
+            (level, counts[counts.len() / 2])
+        })
+        .collect()
+}
+
+/// Sets the character breakdown of words, e.g. 電 "electric" + 腦 "brain" for 電腦. Each
+/// character gets the reading and gloss of its most common entry. Like `entries_by_traditional`,
+/// but grouped after the commonness is known.
+fn resolve_components(entries: &mut [Entry]) {
+    let mut most_common: HashMap<char, &Entry> = HashMap::new();
+    for entry in entries.iter() {
+        let mut chars = entry.traditional.chars();
+        let (Some(cha), None) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        let current = most_common.entry(cha).or_insert(entry);
+        if entry.commonness_boost > current.commonness_boost {
+            *current = entry;
+        }
+    }
+    let components_by_char: HashMap<char, ComponentChar> = most_common
+        .into_iter()
+        .map(|(cha, entry)| (cha, ComponentChar::new(entry)))
+        .collect();
+    for entry in entries.iter_mut() {
+        if entry.traditional.chars().count() < 2 {
+            continue;
+        }
+        // Only complete breakdowns
+        entry.components = entry
+            .traditional
+            .chars()
+            .map(|cha| components_by_char.get(&cha).cloned())
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+    }
+}
+
 fn fix_pinyin(
     entry: &mut Entry,
     entries_by_traditional: &HashMap<char, Vec<Entry>>,
@@ -582,6 +1085,47 @@ impl RemoveWhiteSpace for &str {
     }
 }
 
+/// The WaniKani level of a character, or the maximum level of the characters of a word.
+/// None if any character is not taught on WaniKani.
+fn wk_level(entry: &Entry) -> Option<u32> {
+    if let Some(kanji) = entry.kanji.as_ref() {
+        return kanji.wk_level;
+    }
+    if entry.kanji_chars.is_empty() || entry.kanji_chars.len() != entry.traditional.chars().count()
+    {
+        return None;
+    }
+    entry
+        .kanji_chars
+        .iter()
+        .map(|kanji| kanji.wk_level)
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .max()
+}
+
+/// The on and kun readings (including WaniKani) of a kanji, converted to hiragana.
+/// Kun readings are added with and without okurigana, e.g. "まな.ぶ" -> "まな", "まなぶ"
+fn kana_readings(kanji: &KanjiCharacter) -> Vec<String> {
+    let readings = kanji
+        .readings_on
+        .iter()
+        .chain(kanji.readings_kun.iter())
+        .chain(kanji.wk_readings_on.iter().flatten())
+        .chain(kanji.wk_readings_kun.iter().flatten());
+
+    let mut kana = Vec::new();
+    for reading in readings {
+        // "!" marks WaniKani readings which are not taught, "-" marks prefixes and suffixes
+        let reading = kana::to_hiragana(reading.trim_matches(|c| c == '!' || c == '-' || c == '^'));
+        if let Some((stem, _okurigana)) = reading.split_once('.') {
+            kana.push(stem.to_string());
+        }
+        kana.push(reading.replace('.', ""));
+    }
+    filter_duplicates(kana.into_iter().filter(|el| !el.is_empty()).collect())
+}
+
 fn filter_duplicates(input: Vec<String>) -> Vec<String> {
     let mut result = Vec::new();
     let mut seen = std::collections::HashSet::new();
@@ -612,20 +1156,145 @@ struct Entry {
     zhuyin: String,
     pinyin_pretty: String,
     pinyin_ws_tone_number: String,
+    // Lowercase pinyin without spaces, e.g. "dian4nao3", to find homophones
+    pinyin_compact: String,
+    // Like `pinyin_compact` without tones, e.g. "diannao", to find near-homophones
+    pinyin_toneless: String,
+    // Initial, final and tone of each syllable
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    syllables: Vec<Syllable>,
+    // Tones of the syllables, e.g. "43" for 下午, for the tone-pattern search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tone_pattern: Option<String>,
+    // Final of the last syllable, e.g. "uang" for 上網, for the rhyme search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rhyme: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tocfl_level: Option<u32>,
+    // Level of the Children's Chinese Competency Certification vocabulary, 1 to 3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cccc_level: Option<u32>,
+    // Level in the NAER benchmark word table, 1.0 to 7.0 (second halves of a level are x.5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    naer_level: Option<f32>,
+    // Level in the NAER benchmark character table, for words the maximum of their characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    naer_char_level: Option<f32>,
+    // TOCFL level suggested by the Hacking Chinese list of words missing in TOCFL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hacking_chinese_level: Option<u32>,
+    // HSK 3.0 level, 1 to 9 (the band 7-9 is stored as 7)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hsk_level: Option<u32>,
+    // Legacy HSK 2.0 level, 1 to 6
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hsk2_level: Option<u32>,
+    // Part-of-speech distribution from SUBTLEX-CH, most frequent first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pos: Vec<PosShare>,
     meanings: Vec<String>,
-    meanings_de: Vec<String>,
+    // Split point of separable verbs in characters, e.g. 1 for 睡|覺
+    #[serde(skip_serializing_if = "Option::is_none")]
+    separable_split: Option<usize>,
+    // Literal and figurative meanings of idioms (#chengyu)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    literal_meanings: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    figurative_meanings: Vec<String>,
+    // Meanings from the dictionaries in `../languages.json`, e.g. `meanings_de`
+    #[serde(flatten)]
+    translations: BTreeMap<String, Vec<String>>,
+    // Example sentences from HanDeDict
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    examples: Vec<Example>,
     tags: Vec<String>,
     commonness_boost: f64,
     count_per_million_written: u64,
     count_per_million_spoken: u64,
     count_per_million_in_others: u64,
+    // Count of words missing in TOCFL estimated from their Hacking Chinese level, used in
+    // `commonness_boost` like the TOCFL counts
+    count_per_million_estimated: u64,
+    // SUBTLEX-CH word frequency, split between the readings of ambiguous words
+    subtlex_per_million: f64,
+    // SUBTLEX-CH contextual diversity, percentage of films the word occurs in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subtlex_cd_percentage: Option<f64>,
+    // Character frequency in classical chinese texts
+    classical_per_million: f64,
+    // Like `commonness_boost`, but from the classical frequency. Used in the classical mode.
+    classical_boost: f64,
+    // Frequency on the Zipf scale merged from TOCFL and SUBTLEX-CH, see `zipf`
+    zipf: f64,
     kanji: Option<KanjiCharacter>,
+    // Most common reading and gloss per character for words with multiple characters
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    components: Vec<ComponentChar>,
+    // Kanji data per character for words with multiple characters
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    kanji_chars: Vec<KanjiCharInfo>,
+    // japanese on and kun readings of the kanji in hiragana
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    kana_search: Vec<String>,
+    // JMdict words with the same kanji spelling
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    japanese: Vec<JapaneseEntry>,
 }
 
 type KanjiDict = HashMap<String, KanjiCharacter>;
 
+/// A character of a word with its most common reading and gloss
+#[derive(Serialize, Clone, Debug)]
+struct ComponentChar {
+    traditional: String,
+    simplified: String,
+    pinyin_pretty: String,
+    meaning: String,
+}
+
+impl ComponentChar {
+    fn new(entry: &Entry) -> Self {
+        // Skip glosses like "surname Dian" or "variant of 腦[nao3]", if there's another one
+        let is_gloss =
+            |meaning: &&String| !meaning.starts_with("surname") && !meaning.contains("variant of");
+        let meaning = entry
+            .meanings
+            .iter()
+            .find(is_gloss)
+            .or(entry.meanings.first())
+            .cloned()
+            .unwrap_or_default();
+        Self {
+            traditional: entry.traditional.clone(),
+            simplified: entry.simplified.clone(),
+            pinyin_pretty: entry.pinyin_pretty.clone(),
+            meaning,
+        }
+    }
+}
+
+/// Compact kanji data of a character in a word
+#[derive(Serialize, Clone, Debug)]
+struct KanjiCharInfo {
+    character: char,
+    grade: Option<u32>,
+    jlpt_new: Option<u32>,
+    wk_level: Option<u32>,
+    meanings: Vec<String>,
+}
+
+impl KanjiCharInfo {
+    fn new(character: char, kanji: &KanjiCharacter) -> Self {
+        Self {
+            character,
+            grade: kanji.grade,
+            jlpt_new: kanji.jlpt_new,
+            wk_level: kanji.wk_level,
+            meanings: kanji.meanings.clone(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct KanjiCharacter {
     strokes: u32,
@@ -643,73 +1312,10 @@ struct KanjiCharacter {
     wk_radicals: Option<Vec<String>>,
 }
 
-struct Example {
-    simplified: String,
-    traditional: String,
-    german: String,
-}
-
-struct Definition {
-    definitions: Vec<String>,
-    example: Example,
-}
-
-fn parse_definitions(definitions: Vec<String>) -> Vec<Definition> {
-    let mut parsed_definitions = Vec::new();
-
-    for def in definitions {
-        let (def, examples) = def.split_once("Bsp.:").unwrap();
-        let parts: Vec<_> = def
-            .split(";")
-            .filter(|split| split.trim().is_empty())
-            .collect();
-        //let parts: Vec<&str> = def.split(";").collect();
-
-        let example_parts = parts.last().unwrap().split("--").collect::<Vec<&str>>();
-
-        // Assuming the format is always consistent
-        let example = Example {
-            simplified: example_parts[0].trim().to_string(),
-            traditional: example_parts[1].trim().to_string(),
-            german: example_parts[2].trim().to_string(),
-        };
-
-        // Remove the example part and collect the rest as definitions
-        let defs = parts[..parts.len() - 1]
-            .iter()
-            .map(|&s| s.trim().to_string())
-            .collect();
-
-        parsed_definitions.push(Definition {
-            definitions: defs,
-            example,
-        });
-    }
-
-    parsed_definitions
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_definitions() {
-        let definitions = vec![
-            "Familie; Haushalt (S); Bsp.: 我家有四口人。 我家有四口人。 -- Wir sind eine vierköpfige Familie.".to_string(),
-            // Add other examples here
-        ];
-
-        let parsed = parse_definitions(definitions);
-
-        assert_eq!(parsed.len(), 1);
-        let first = &parsed[0];
-        assert_eq!(first.definitions, vec!["Familie", "Haushalt"]);
-        assert_eq!(first.example.simplified, "我家有四口人。");
-        assert_eq!(first.example.traditional, "我家有四口人。");
-        assert_eq!(first.example.german, "Wir sind eine vierköpfige Familie.");
-    }
-
     #[test]
     fn test_normalize_def() {
         let pinyin = normalize_definitions_and_extract_taiwan_pinyin(&mut vec![
@@ -717,4 +1323,26 @@ mod tests {
         ]);
         assert_eq!(pinyin, Some("qǐ lai".to_string()));
     }
+
+    #[test]
+    fn test_subtlex_boost_without_tocfl() {
+        // 混蛋 is not in TOCFL, the SUBTLEX count still ranks it above unknown words
+        let commonness = get_commonness();
+        let radicals = Radicals {
+            traditional_to_radicals: HashMap::new(),
+            simplified_to_radicals: HashMap::new(),
+        };
+        let mut entry = create_entry(
+            "混蛋",
+            "混蛋",
+            "hun2 dan4",
+            vec!["scoundrel".to_string()],
+            &KanjiDict::new(),
+            &radicals,
+        );
+        resolve_subtlex_frequency(&mut entry, &commonness, 1);
+        assert!(entry.tocfl_level.is_none());
+        assert!(entry.subtlex_per_million > 0.0);
+        assert!(commonness_boost(&entry) > 1.0);
+    }
 }
@@ -0,0 +1,177 @@
+//! Taiwan benchmark for the Chinese language proficiency (臺灣華語文能力基準) of the National
+//! Academy for Educational Research (NAER), the successor of the TOCFL lists.
+//!
+//! The word table (詞語表) and the character table (漢字表) assign a band (等別: 基礎, 進階, 精熟)
+//! and a level (級別: 第1級 .. 第7級) to every word and character. The basic levels are split in
+//! two halves, e.g. 第1級 and 第1*級, which are stored as 1.0 and 1.5 to keep them sortable.
+
+use std::collections::{HashMap, HashSet};
+
+use calamine::{open_workbook_auto, DataType, Reader};
+
+use crate::cccc::{expand_forms, toneless_pinyin};
+
+const NAER_WORDS_PATH: &str = "臺灣華語文能力基準詞語表_111-11-14, grade 3 and 7.xlsx";
+const NAER_CHARS_PATH: &str =
+    "臺灣華語文能力基準漢字表_111-09-20.chinese.character.table.coct.naer.edu.tw.xlsx";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NaerLevel {
+    pub band: NaerBand,
+    // 1.0 to 7.0, the second half of a level is x.5
+    pub level: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NaerBand {
+    Basic,
+    Advanced,
+    Proficient,
+}
+
+impl NaerBand {
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "基礎" => Some(NaerBand::Basic),
+            "進階" => Some(NaerBand::Advanced),
+            "精熟" => Some(NaerBand::Proficient),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NaerBand::Basic => "Basic",
+            NaerBand::Advanced => "Advanced",
+            NaerBand::Proficient => "Proficient",
+        }
+    }
+}
+
+/// Parses the level, e.g. "第1級" to 1.0 and "第1*級" to 1.5
+fn parse_level(text: &str) -> Option<f32> {
+    let text = text.trim().strip_prefix('第')?.strip_suffix('級')?;
+    let (num, half) = match text.strip_suffix(['*', '※']) {
+        Some(num) => (num, 0.5),
+        None => (text, 0.0),
+    };
+    Some(num.parse::<f32>().ok()? + half)
+}
+
+fn parse_naer_level(band: &DataType, level: &DataType) -> Option<NaerLevel> {
+    let (DataType::String(band), DataType::String(level)) = (band, level) else {
+        return None;
+    };
+    Some(NaerLevel {
+        band: NaerBand::parse(band)?,
+        level: parse_level(level)?,
+    })
+}
+
+#[derive(Debug)]
+struct NaerWord {
+    level: NaerLevel,
+    // Toneless pinyin of all forms
+    pinyin: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct NaerDictionary {
+    words: HashMap<String, Vec<NaerWord>>,
+    chars: HashMap<char, NaerLevel>,
+}
+
+impl NaerDictionary {
+    /// Returns the level of the word. Words with multiple readings need to match the pinyin,
+    /// e.g. 背 bèi (back) and bēi (to carry) have different levels.
+    pub fn get_word_level(
+        &self,
+        traditional: &str,
+        pinyin_ws_tone_number: &str,
+        is_unambiguous: bool,
+    ) -> Option<NaerLevel> {
+        let words = self.words.get(traditional)?;
+        let pinyin = toneless_pinyin(pinyin_ws_tone_number);
+        words
+            .iter()
+            .filter(|word| is_unambiguous || word.pinyin.contains(&pinyin))
+            .map(|word| word.level)
+            .min_by(|a, b| a.level.total_cmp(&b.level))
+    }
+
+    /// Returns the character level, for words the maximum level of their characters, so the
+    /// word is readable once all characters of that level are learned.
+    pub fn get_char_level(&self, traditional: &str) -> Option<NaerLevel> {
+        traditional
+            .chars()
+            .map(|cha| self.chars.get(&cha).copied())
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max_by(|a, b| a.level.total_cmp(&b.level))
+    }
+}
+
+pub fn load_naer_dictionary() -> NaerDictionary {
+    let mut dict = NaerDictionary::default();
+    if !std::path::Path::new(NAER_WORDS_PATH).exists()
+        || !std::path::Path::new(NAER_CHARS_PATH).exists()
+    {
+        println!("Skipping missing NAER benchmark tables");
+        return dict;
+    }
+
+    let mut workbook = open_workbook_auto(NAER_WORDS_PATH).unwrap();
+    let range = workbook.worksheet_range("總詞表").unwrap().unwrap();
+    // Columns: 序號, 詞語, 等別, 級別, 情境, 書面字頻, 口語字頻, 簡編本系統號, 參考注音, 參考漢語拼音
+    for row in range.rows().skip(1) {
+        let (Some(DataType::String(word)), Some(DataType::String(pinyin))) =
+            (row.get(1), row.get(9))
+        else {
+            continue;
+        };
+        let Some(level) = parse_naer_level(&row[2], &row[3]) else {
+            continue;
+        };
+        // Homographs are numbered, e.g. 背1 and 背2
+        let word = word.trim_end_matches(|cha: char| cha.is_ascii_digit());
+        let pinyin: HashSet<String> = expand_forms(pinyin)
+            .iter()
+            .map(|pinyin| toneless_pinyin(pinyin))
+            .collect();
+        for form in expand_forms(word) {
+            dict.words.entry(form).or_default().push(NaerWord {
+                level,
+                pinyin: pinyin.clone(),
+            });
+        }
+    }
+
+    let mut workbook = open_workbook_auto(NAER_CHARS_PATH).unwrap();
+    let range = workbook.worksheet_range("工作表1").unwrap().unwrap();
+    // Columns: 序號, 漢字, 等別, 級別, 書面字頻, 口語字頻
+    for row in range.rows().skip(1) {
+        let Some(DataType::String(cha)) = row.get(1) else {
+            continue;
+        };
+        let (Some(cha), Some(level)) = (
+            cha.trim().chars().next(),
+            parse_naer_level(&row[2], &row[3]),
+        ) else {
+            continue;
+        };
+        dict.chars.insert(cha, level);
+    }
+    dict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("第1級"), Some(1.0));
+        assert_eq!(parse_level("第3*級"), Some(3.5));
+        assert_eq!(parse_level("核心詞"), None);
+    }
+}
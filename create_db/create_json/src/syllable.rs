@@ -0,0 +1,169 @@
+//! Decomposition of pinyin syllables with tone numbers into initial, final and tone, e.g. `xia4`
+//! into `x`, `ia` and 4. Used for the tone-pattern and rhyme search.
+//!
+//! The finals are the full finals of the pinyin table, without the spelling rules: `y` and `w`
+//! are not initials, so `yang` has the final `iang` and `wang` the final `uang`. `yu`, `ju`, `qu`
+//! and `xu` have the final `ü`, and the contractions `you`/`-iu`, `wei`/`-ui` and `wen`/`-un` are
+//! expanded to `iou`, `uei` and `uen`. The apical vowel of `zhi`, `chi`, `shi`, `ri`, `zi`, `ci`
+//! and `si` is written `-i`, to keep it apart from the `i` of `ji` or `yi`.
+
+use serde::Serialize;
+
+/// Two-letter initials first
+const INITIALS: [&str; 21] = [
+    "zh", "ch", "sh", "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "j", "q", "x", "r",
+    "z", "c", "s",
+];
+
+/// Initials followed by the apical vowel `-i` instead of `i`
+const APICAL_INITIALS: [&str; 7] = ["zh", "ch", "sh", "r", "z", "c", "s"];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Syllable {
+    pub initial: String,
+    #[serde(rename = "final")]
+    pub final_: String,
+    /// 1 to 4, 5 for the neutral tone
+    pub tone: u32,
+}
+
+/// Splits a syllable like `xia4`, `lu:4` or `r5`. None if the tone number is missing.
+pub fn split_syllable(syllable: &str) -> Option<Syllable> {
+    let syllable = syllable.to_lowercase().replace("u:", "ü");
+    let tone = syllable
+        .chars()
+        .last()?
+        .to_digit(10)
+        .filter(|tone| (1..=5).contains(tone))?;
+    let letters = &syllable[..syllable.len() - 1];
+    if letters.is_empty()
+        || !letters
+            .chars()
+            .all(|cha| cha.is_ascii_lowercase() || cha == 'ü')
+    {
+        return None;
+    }
+    // Syllabic consonants like `r5` or `m2` have no initial
+    let initial = INITIALS
+        .iter()
+        .find(|initial| letters.starts_with(*initial) && letters.len() > initial.len())
+        .unwrap_or(&"");
+    Some(Syllable {
+        initial: initial.to_string(),
+        final_: full_final(initial, &letters[initial.len()..]),
+        tone,
+    })
+}
+
+/// Undoes the spelling rules of pinyin, e.g. `ang` after `y` is `iang` and `u` after `j` is `ü`
+fn full_final(initial: &str, spelled: &str) -> String {
+    if initial.is_empty() {
+        if let Some(rest) = spelled.strip_prefix('y') {
+            return match rest {
+                "ou" => "iou".to_string(),
+                _ if rest.starts_with('u') => format!("ü{}", &rest[1..]),
+                _ if rest.starts_with('i') => rest.to_string(),
+                _ => format!("i{}", rest),
+            };
+        }
+        if let Some(rest) = spelled.strip_prefix('w') {
+            return match rest {
+                "u" => "u".to_string(),
+                _ => format!("u{}", rest),
+            };
+        }
+        return spelled.to_string();
+    }
+    if ["j", "q", "x"].contains(&initial) && spelled.starts_with('u') {
+        return format!("ü{}", &spelled[1..]);
+    }
+    if APICAL_INITIALS.contains(&initial) && spelled == "i" {
+        return "-i".to_string();
+    }
+    match spelled {
+        "iu" => "iou".to_string(),
+        "ui" => "uei".to_string(),
+        "un" => "uen".to_string(),
+        _ => spelled.to_string(),
+    }
+}
+
+/// Splits all syllables of the pinyin, e.g. `xia4 wu3`. None if any syllable can't be split,
+/// e.g. for entries with latin letters.
+pub fn split_syllables(pinyin: &str) -> Option<Vec<Syllable>> {
+    pinyin.split_whitespace().map(split_syllable).collect()
+}
+
+/// The tones of the syllables, e.g. "43" for 下午
+pub fn tone_pattern(syllables: &[Syllable]) -> String {
+    syllables
+        .iter()
+        .map(|syllable| syllable.tone.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_syllable() {
+        let syllable = split_syllable("zhuang1").unwrap();
+        assert_eq!(
+            (
+                syllable.initial.as_str(),
+                syllable.final_.as_str(),
+                syllable.tone
+            ),
+            ("zh", "uang", 1)
+        );
+        let syllable = split_syllable("xiang2").unwrap();
+        assert_eq!(
+            (syllable.initial.as_str(), syllable.final_.as_str()),
+            ("x", "iang")
+        );
+        let syllable = split_syllable("ju4").unwrap();
+        assert_eq!(
+            (syllable.initial.as_str(), syllable.final_.as_str()),
+            ("j", "ü")
+        );
+        assert_eq!(split_syllable("Lu:4").unwrap().final_, "ü");
+        assert_eq!(split_syllable("lu4").unwrap().final_, "u");
+        assert_eq!(split_syllable("er2").unwrap().initial, "");
+        assert_eq!(split_syllable("r5").unwrap().final_, "r");
+        assert_eq!(split_syllable("xia"), None);
+    }
+
+    #[test]
+    fn test_full_final() {
+        let finals = |pinyin: &str| -> Vec<String> {
+            split_syllables(pinyin)
+                .unwrap()
+                .into_iter()
+                .map(|syllable| syllable.final_)
+                .collect()
+        };
+        assert_eq!(
+            finals("yang2 wang2 yu2 yue4 yuan2 yun2 yi1 yin1 ying1 you3 wei4 wen4 wu3"),
+            vec![
+                "iang", "uang", "ü", "üe", "üan", "ün", "i", "in", "ing", "iou", "uei", "uen", "u"
+            ]
+        );
+        assert_eq!(
+            finals("qu4 xue2 jun1 liu4 gui4 lun4"),
+            vec!["ü", "üe", "ün", "iou", "uei", "uen"]
+        );
+        assert_eq!(
+            finals("zhi1 chi1 shi4 ri4 zi4 ci4 si4 ji1"),
+            vec!["-i", "-i", "-i", "-i", "-i", "-i", "-i", "i"]
+        );
+        assert_eq!(split_syllable("1"), None);
+    }
+
+    #[test]
+    fn test_tone_pattern() {
+        let syllables = split_syllables("xia4 wu3").unwrap();
+        assert_eq!(tone_pattern(&syllables), "43");
+        assert_eq!(split_syllables("T xu4"), None);
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// German word classes of HanDeDict, e.g. "Haushalt (S)"
+static WORD_CLASS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\s*\((S|V|Adj|Adv|Pron|Num|Zähl|Präp|Konj|Int|Part|Eig)\)$").unwrap()
+});
+
+/// CEDICT-format dictionaries in other languages, shared with the webpage.
+/// Each language produces a `meanings_{lang}` field.
+pub const LANGUAGES_PATH: &str = "../languages.json";
+
+#[derive(Debug, Deserialize)]
+pub struct TranslationDictConfig {
+    /// Language code, used for the `meanings_{lang}` field
+    pub lang: String,
+    /// Path relative to `create_db`
+    pub path: String,
+    /// Add entries for headwords that are missing in CC-CEDICT
+    pub merge_missing: bool,
+    /// Remove the german word classes of HanDeDict, e.g. "(S)". Off by default, which keeps the
+    /// definitions as they are in the dictionary.
+    #[serde(default)]
+    pub strip_word_classes: bool,
+}
+
+fn load_translation_dict_configs(path: &str) -> Vec<TranslationDictConfig> {
+    let text =
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("Invalid {}: {}", path, e))
+}
+
+pub struct TranslationEntry {
+    pub simplified: String,
+    pub traditional: String,
+    pub pinyin: String,
+    pub meanings: Vec<String>,
+    pub examples: Vec<Example>,
+}
+
+type Key = (String, String);
+
+pub struct TranslationDict {
+    pub lang: String,
+    pub merge_missing: bool,
+    // Key is (Traditional, Pinyin)
+    entries: HashMap<Key, TranslationEntry>,
+    // (Traditional, normalized Pinyin) -> Keys in entries, in file order
+    normalized_keys: HashMap<Key, Vec<Key>>,
+    matched: HashSet<Key>,
+    fuzzy_matched: HashSet<Key>,
+}
+
+impl TranslationDict {
+    /// The first entry in file order wins if a key is duplicated
+    fn new(lang: &str, merge_missing: bool, file_entries: Vec<TranslationEntry>) -> Self {
+        let mut entries: HashMap<Key, TranslationEntry> = HashMap::new();
+        let mut normalized_keys: HashMap<Key, Vec<Key>> = HashMap::new();
+        for entry in file_entries {
+            let key = (entry.traditional.to_string(), entry.pinyin.to_string());
+            if entries.contains_key(&key) {
+                continue;
+            }
+            normalized_keys
+                .entry((key.0.to_string(), normalize_pinyin(&key.1)))
+                .or_default()
+                .push(key.clone());
+            entries.insert(key, entry);
+        }
+        TranslationDict {
+            lang: lang.to_string(),
+            merge_missing,
+            entries,
+            normalized_keys,
+            matched: HashSet::new(),
+            fuzzy_matched: HashSet::new(),
+        }
+    }
+
+    /// Returns the translation for a CC-CEDICT entry. If there is no entry with the exact pinyin,
+    /// the pinyin is normalized before matching, e.g. "nu:3 er2" matches "nü3er2". The normalized
+    /// match is only used if it is unique.
+    pub fn get(&mut self, traditional: &str, pinyin: &str) -> Option<&TranslationEntry> {
+        let key = (traditional.to_string(), pinyin.to_string());
+        if self.entries.contains_key(&key) {
+            self.matched.insert(key.clone());
+            return self.entries.get(&key);
+        }
+        let normalized_key = (traditional.to_string(), normalize_pinyin(pinyin));
+        let [key] = self.normalized_keys.get(&normalized_key)?.as_slice() else {
+            return None;
+        };
+        self.fuzzy_matched.insert(key.clone());
+        self.entries.get(key)
+    }
+
+    /// True if the dictionary file is missing
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries which did not match any CC-CEDICT entry, sorted by (traditional, pinyin) so
+    /// the order of `db.json` is stable between builds
+    pub fn unmatched_entries(&self) -> Vec<&TranslationEntry> {
+        let mut unmatched: Vec<(&Key, &TranslationEntry)> = self
+            .entries
+            .iter()
+            .filter(|(key, _)| !self.matched.contains(*key) && !self.fuzzy_matched.contains(*key))
+            .collect();
+        unmatched.sort_by(|a, b| a.0.cmp(b.0));
+        unmatched.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn print_report(&self) {
+        let fuzzy_matched = self
+            .fuzzy_matched
+            .iter()
+            .filter(|key| !self.matched.contains(*key))
+            .count();
+        let unmatched = self.entries.len() - self.matched.len() - fuzzy_matched;
+        println!(
+            "Dictionary {}: {} entries, {} matched, {} matched after normalizing pinyin, {} {}",
+            self.lang,
+            self.entries.len(),
+            self.matched.len(),
+            fuzzy_matched,
+            unmatched,
+            if self.merge_missing {
+                "added"
+            } else {
+                "dropped"
+            }
+        );
+    }
+}
+
+/// Normalizes the pinyin for matching between dictionaries, by ignoring whitespace, the neutral
+/// tone number and the different spellings of ü. The case is kept, so the surname reading Li3
+/// doesn't match li3.
+fn normalize_pinyin(pinyin: &str) -> String {
+    pinyin
+        .replace("U:", "V")
+        .replace('Ü', "V")
+        .replace("u:", "v")
+        .replace('ü', "v")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '5')
+        .collect()
+}
+
+/// Loads all dictionaries in `LANGUAGES_PATH`. Missing files are skipped, their
+/// `meanings_{lang}` field is left out.
+pub fn get_translation_dicts() -> Vec<TranslationDict> {
+    load_translation_dict_configs(LANGUAGES_PATH)
+        .iter()
+        .map(|config| {
+            let path = std::path::Path::new("..").join(&config.path);
+            let file_entries = if path.exists() {
+                get_translation_dict(&path, config)
+            } else {
+                println!(
+                    "Skipping missing dictionary {} for language {}",
+                    path.display(),
+                    config.lang
+                );
+                Vec::new()
+            };
+            TranslationDict::new(&config.lang, config.merge_missing, file_entries)
+        })
+        .collect()
+}
+
+/// The entries of the dictionary in file order
+fn get_translation_dict(
+    path: &std::path::Path,
+    config: &TranslationDictConfig,
+) -> Vec<TranslationEntry> {
+    let mut dict = Vec::new();
+    let all = std::fs::read_to_string(path).unwrap();
+    for line in all.lines() {
+        let parsed = cedict::parse_line(line);
+        let e = match parsed {
+            cedict::Line::Entry(e) => e,
+            cedict::Line::Comment(_) | cedict::Line::Metadata(_, _) | cedict::Line::Empty => {
+                continue;
+            }
+            cedict::Line::Incorrect => {
+                continue;
+            }
+        };
+        let pinyin_ws_tone_number = e.pinyin().to_string();
+        let traditional = e.traditional().to_string();
+
+        // Definitions with an example ("Bsp.:") are split into the meanings and the example
+        let mut meanings = Vec::new();
+        let mut examples = Vec::new();
+        for def in e.definitions() {
+            if let Some(definition) = parse_definition(def, config) {
+                meanings.extend(definition.definitions);
+                examples.extend(definition.example);
+            } else if config.strip_word_classes {
+                meanings.extend(split_definition(def, true));
+            } else {
+                meanings.push(def.to_string());
+            }
+        }
+        let entry = TranslationEntry {
+            simplified: e.simplified().to_string(),
+            traditional: traditional.to_string(),
+            pinyin: pinyin_ws_tone_number.to_string(),
+            meanings,
+            examples,
+        };
+        dict.push(entry);
+    }
+    dict
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Example {
+    simplified: String,
+    traditional: String,
+    /// The example in the language of the dictionary
+    translation: String,
+    /// Language code of the translation, e.g. "de"
+    lang: String,
+}
+
+struct Definition {
+    definitions: Vec<String>,
+    /// None if the example can't be split into the simplified and traditional sentence
+    example: Option<Example>,
+}
+
+/// HanDeDict appends examples to the definitions in the form
+/// "Familie; Haushalt (S); Bsp.: 我家有四口人。 我家有四口人。 -- Wir sind eine vierköpfige Familie."
+///
+/// Returns None if the definition has no example.
+fn parse_definition(def: &str, config: &TranslationDictConfig) -> Option<Definition> {
+    let (def, example) = def.split_once("Bsp.:")?;
+    let (chinese, translation) = example.split_once("--")?;
+
+    let example = split_example(chinese).map(|(simplified, traditional)| Example {
+        simplified,
+        traditional,
+        translation: translation.trim().to_string(),
+        lang: config.lang.to_string(),
+    });
+
+    Some(Definition {
+        definitions: split_definition(def, config.strip_word_classes),
+        example,
+    })
+}
+
+/// Splits the chinese part of an example into the simplified and the traditional sentence, which
+/// are separated by a space. The sentences can contain spaces too, e.g. around latin words, so
+/// the separator is the space where both halves have the same number of characters.
+///
+/// Returns None if there is no such space.
+fn split_example(chinese: &str) -> Option<(String, String)> {
+    let words = chinese.split_whitespace().collect::<Vec<_>>();
+    let num_chars = |words: &[&str]| words.iter().map(|word| word.chars().count()).sum::<usize>();
+    (1..words.len())
+        .map(|i| words.split_at(i))
+        .find(|(simplified, traditional)| {
+            simplified.len() == traditional.len() && num_chars(simplified) == num_chars(traditional)
+        })
+        .map(|(simplified, traditional)| (simplified.join(" "), traditional.join(" ")))
+}
+
+/// Splits semicolon separated definitions and optionally removes the word class, e.g.
+/// "Haushalt (S)"
+fn split_definition(def: &str, strip_word_classes: bool) -> Vec<String> {
+    def.split(';')
+        .map(|part| {
+            if strip_word_classes {
+                WORD_CLASS.replace(part.trim(), "").to_string()
+            } else {
+                part.trim().to_string()
+            }
+        })
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_definitions() {
+        let handedict = &TranslationDictConfig {
+            lang: "de".to_string(),
+            path: "handedict.u8".to_string(),
+            merge_missing: true,
+            strip_word_classes: true,
+        };
+        let first = parse_definition(
+            "Familie; Haushalt (S); Bsp.: 我家有四口人。 我家有四口人。 -- Wir sind eine vierköpfige Familie.",
+            handedict,
+        )
+        .unwrap();
+
+        assert_eq!(first.definitions, vec!["Familie", "Haushalt"]);
+        let example = first.example.unwrap();
+        assert_eq!(example.simplified, "我家有四口人。");
+        assert_eq!(example.traditional, "我家有四口人。");
+        assert_eq!(example.translation, "Wir sind eine vierköpfige Familie.");
+        assert_eq!(example.lang, "de");
+
+        // The sentences contain spaces themselves
+        let multi_word = parse_definition(
+            "benutzen (V); Bsp.: 我用 Linux 系统 。 我用 Linux 系統 。 -- Ich benutze Linux.",
+            handedict,
+        )
+        .unwrap()
+        .example
+        .unwrap();
+        assert_eq!(multi_word.simplified, "我用 Linux 系统 。");
+        assert_eq!(multi_word.traditional, "我用 Linux 系統 。");
+        // Examples which can't be split are dropped, the definitions are kept
+        let unsplittable = parse_definition(
+            "Familie (S); Bsp.: 我家 有四口人。 -- Wir sind vier.",
+            handedict,
+        )
+        .unwrap();
+        assert_eq!(unsplittable.definitions, vec!["Familie"]);
+        assert!(unsplittable.example.is_none());
+
+        assert!(parse_definition("Familie; Haushalt (S)", handedict).is_none());
+        assert_eq!(
+            split_definition("Familie; Haushalt (S)", true),
+            vec!["Familie", "Haushalt"]
+        );
+        // Only HanDeDict has word classes
+        assert_eq!(split_definition("Anna (V)", false), vec!["Anna (V)"]);
+    }
+
+    #[test]
+    fn test_load_configs() {
+        let configs = load_translation_dict_configs(LANGUAGES_PATH);
+        assert!(configs.iter().any(|config| config.lang == "de"));
+    }
+
+    #[test]
+    fn test_get_translation() {
+        let entry = |pinyin: &str, meaning: &str| TranslationEntry {
+            simplified: "女儿".to_string(),
+            traditional: "女兒".to_string(),
+            pinyin: pinyin.to_string(),
+            meanings: vec![meaning.to_string()],
+            examples: Vec::new(),
+        };
+        let mut dict = TranslationDict::new(
+            "de",
+            false,
+            vec![
+                entry("nu:3 er2", "Tochter"),
+                entry("nu:3 er2", "Duplikat"),
+                entry("Nu:3 er2", "Name"),
+            ],
+        );
+        assert_eq!(
+            dict.get("女兒", "nu:3 er2").unwrap().meanings,
+            vec!["Tochter"]
+        );
+        assert_eq!(
+            dict.get("女兒", "nü3 er2").unwrap().meanings,
+            vec!["Tochter"]
+        );
+        assert_eq!(dict.get("女兒", "Nu:3 er2").unwrap().meanings, vec!["Name"]);
+        // Ambiguous after normalizing
+        let mut dict = TranslationDict::new(
+            "de",
+            false,
+            vec![entry("nu:3 er2", "Tochter"), entry("nü3 er2", "Mädchen")],
+        );
+        assert!(dict.get("女兒", "nu:3 er5").is_none());
+    }
+
+    #[test]
+    fn test_normalize_pinyin() {
+        assert_eq!(normalize_pinyin("nu:3 er2"), normalize_pinyin("nü3er2"));
+        assert_eq!(normalize_pinyin("ma5"), normalize_pinyin("ma"));
+        assert_ne!(normalize_pinyin("Li3"), normalize_pinyin("li3"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Conversion of japanese queries to hiragana, to search the japanese readings of the
+//! characters in `kana_search[]`. E.g. ガク, がく and gaku are all converted to がく.
+//! Romaji can be marked explicitly with `romaji:`, e.g. `romaji:gaku`.
+
+/// Hepburn romaji to hiragana, longest syllables first
+const ROMAJI_TABLE: &[(&str, &str)] = &[
+    ("kya", "きゃ"),
+    ("kyu", "きゅ"),
+    ("kyo", "きょ"),
+    ("gya", "ぎゃ"),
+    ("gyu", "ぎゅ"),
+    ("gyo", "ぎょ"),
+    ("sha", "しゃ"),
+    ("shi", "し"),
+    ("shu", "しゅ"),
+    ("sho", "しょ"),
+    ("chi", "ち"),
+    ("cha", "ちゃ"),
+    ("chu", "ちゅ"),
+    ("cho", "ちょ"),
+    ("tsu", "つ"),
+    ("nya", "にゃ"),
+    ("nyu", "にゅ"),
+    ("nyo", "にょ"),
+    ("hya", "ひゃ"),
+    ("hyu", "ひゅ"),
+    ("hyo", "ひょ"),
+    ("bya", "びゃ"),
+    ("byu", "びゅ"),
+    ("byo", "びょ"),
+    ("pya", "ぴゃ"),
+    ("pyu", "ぴゅ"),
+    ("pyo", "ぴょ"),
+    ("mya", "みゃ"),
+    ("myu", "みゅ"),
+    ("myo", "みょ"),
+    ("rya", "りゃ"),
+    ("ryu", "りゅ"),
+    ("ryo", "りょ"),
+    ("ja", "じゃ"),
+    ("ji", "じ"),
+    ("ju", "じゅ"),
+    ("jo", "じょ"),
+    ("ka", "か"),
+    ("ki", "き"),
+    ("ku", "く"),
+    ("ke", "け"),
+    ("ko", "こ"),
+    ("ga", "が"),
+    ("gi", "ぎ"),
+    ("gu", "ぐ"),
+    ("ge", "げ"),
+    ("go", "ご"),
+    ("sa", "さ"),
+    ("su", "す"),
+    ("se", "せ"),
+    ("so", "そ"),
+    ("za", "ざ"),
+    ("zu", "ず"),
+    ("ze", "ぜ"),
+    ("zo", "ぞ"),
+    ("ta", "た"),
+    ("te", "て"),
+    ("to", "と"),
+    ("da", "だ"),
+    ("de", "で"),
+    ("do", "ど"),
+    ("na", "な"),
+    ("ni", "に"),
+    ("nu", "ぬ"),
+    ("ne", "ね"),
+    ("no", "の"),
+    ("ha", "は"),
+    ("hi", "ひ"),
+    ("fu", "ふ"),
+    ("he", "へ"),
+    ("ho", "ほ"),
+    ("ba", "ば"),
+    ("bi", "び"),
+    ("bu", "ぶ"),
+    ("be", "べ"),
+    ("bo", "ぼ"),
+    ("pa", "ぱ"),
+    ("pi", "ぴ"),
+    ("pu", "ぷ"),
+    ("pe", "ぺ"),
+    ("po", "ぽ"),
+    ("ma", "ま"),
+    ("mi", "み"),
+    ("mu", "む"),
+    ("me", "め"),
+    ("mo", "も"),
+    ("ya", "や"),
+    ("yu", "ゆ"),
+    ("yo", "よ"),
+    ("ra", "ら"),
+    ("ri", "り"),
+    ("ru", "る"),
+    ("re", "れ"),
+    ("ro", "ろ"),
+    ("wa", "わ"),
+    ("wo", "を"),
+    ("nn", "ん"),
+    ("n'", "ん"),
+    ("a", "あ"),
+    ("i", "い"),
+    ("u", "う"),
+    ("e", "え"),
+    ("o", "お"),
+    ("n", "ん"),
+];
+
+pub fn is_kana(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|cha| matches!(cha as u32, 0x3041..=0x3096 | 0x30A1..=0x30FA | 0x30FC))
+}
+
+/// Converts katakana to hiragana, other characters are kept
+pub fn to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|cha| match cha as u32 {
+            0x30A1..=0x30F6 => char::from_u32(cha as u32 - 0x60).unwrap_or(cha),
+            _ => cha,
+        })
+        .collect()
+}
+
+/// Converts romaji to hiragana, e.g. "gaku" to "がく".
+///
+/// Returns None if the text can't be read as romaji, e.g. "xiang". Many english and pinyin words
+/// are valid romaji too, e.g. "home" is converted to "ほめ".
+pub fn romaji_to_hiragana(text: &str) -> Option<String> {
+    let text = text.to_lowercase();
+    let mut rest = text.as_str();
+    let mut hiragana = String::new();
+    while !rest.is_empty() {
+        // Double consonant, e.g. "gakki" -> がっき
+        let mut chars = rest.chars();
+        if let (Some(first), Some(second)) = (chars.next(), chars.next()) {
+            if first == second && first.is_ascii_alphabetic() && !"aeioun".contains(first) {
+                hiragana.push('っ');
+                rest = &rest[first.len_utf8()..];
+                continue;
+            }
+        }
+        let (romaji, kana) = ROMAJI_TABLE
+            .iter()
+            .find(|(romaji, _)| rest.starts_with(romaji))?;
+        // "n" followed by a vowel or "y" is the start of the next syllable, e.g. "na" or "nya"
+        if *romaji == "n" && rest[1..].starts_with(['a', 'i', 'u', 'e', 'o', 'y']) {
+            return None;
+        }
+        hiragana.push_str(kana);
+        rest = &rest[romaji.len()..];
+    }
+    Some(hiragana)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_romaji_to_hiragana() {
+        assert_eq!(romaji_to_hiragana("gaku"), Some("がく".to_string()));
+        assert_eq!(romaji_to_hiragana("shin"), Some("しん".to_string()));
+        assert_eq!(romaji_to_hiragana("gakkou"), Some("がっこう".to_string()));
+        assert_eq!(romaji_to_hiragana("xiang"), None);
+        assert_eq!(romaji_to_hiragana("home"), Some("ほめ".to_string()));
+    }
+
+    #[test]
+    fn test_to_hiragana() {
+        assert!(is_kana("ガク"));
+        assert_eq!(to_hiragana("ガク"), "がく");
+    }
+}
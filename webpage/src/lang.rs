@@ -0,0 +1,184 @@
+use core::fmt;
+use std::str::FromStr;
+
+use axum::http::{header, HeaderMap};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::search;
+
+/// Name of the cookie which stores the language preference
+pub const LANG_COOKIE: &str = "lang";
+
+/// Returns the value of the cookie with the name
+pub fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value)
+}
+
+/// The languages of the translation dictionaries, shared with `create_json`
+const LANGUAGES_PATH: &str = "../create_db/languages.json";
+
+#[derive(Deserialize)]
+struct LanguageConfig {
+    lang: String,
+    name: String,
+}
+
+/// English and the configured languages, which have a `meanings_{lang}` field in the index.
+/// Languages without a dictionary in the build are skipped.
+static LANGUAGES: Lazy<Vec<Language>> = Lazy::new(|| {
+    let configs: Vec<LanguageConfig> = match std::fs::read_to_string(LANGUAGES_PATH) {
+        Ok(text) => serde_json::from_str(&text).expect("invalid languages.json"),
+        Err(e) => {
+            log::warn!("Could not read {}: {}", LANGUAGES_PATH, e);
+            Vec::new()
+        }
+    };
+    let configured = configs.into_iter().map(|config| Language {
+        code: Box::leak(config.lang.into_boxed_str()),
+        name: Box::leak(config.name.into_boxed_str()),
+    });
+    std::iter::once(Language::EN)
+        .chain(configured.filter(|lang| search::has_field(&lang.meanings_path())))
+        .collect()
+});
+
+/// The language preference of the user.
+///
+/// It switches the displayed meanings, the UI strings and the ranking weight between
+/// `meanings[]` and the `meanings_{lang}[]` fields.
+///
+/// Every language except english has a CEDICT-format dictionary configured in
+/// `create_db/languages.json`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Language {
+    code: &'static str,
+    name: &'static str,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Language::EN
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_lowercase();
+        Language::all()
+            .iter()
+            .find(|lang| lang.code == code)
+            .copied()
+            .ok_or_else(|| format!("Unknown language {}", code))
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Language {
+    /// CC-CEDICT, the `meanings` field
+    pub const EN: Language = Language {
+        code: "en",
+        name: "English",
+    };
+
+    pub fn all() -> &'static [Language] {
+        &LANGUAGES
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Name of the language in the language itself, used for the language switch
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The UI is translated to german only
+    pub fn has_german_ui(&self) -> bool {
+        self.code == "de"
+    }
+
+    /// Reads the language preference from the `lang` cookie
+    pub fn from_cookie(headers: &HeaderMap) -> Option<Language> {
+        cookie_value(headers, LANG_COOKIE).and_then(|value| value.parse().ok())
+    }
+
+    /// The `Set-Cookie` header value to persist the language preference for a year
+    pub fn to_cookie(self) -> String {
+        format!(
+            "{}={}; Path=/; Max-Age=31536000; SameSite=Lax",
+            LANG_COOKIE,
+            self.code()
+        )
+    }
+
+    /// The field with the meanings in this language
+    pub fn meanings_field(&self) -> String {
+        if *self == Language::EN {
+            "meanings".to_string()
+        } else {
+            format!("meanings_{}", self.code())
+        }
+    }
+
+    /// The search path of the meanings in this language
+    pub fn meanings_path(&self) -> String {
+        format!("{}[]", self.meanings_field())
+    }
+
+    /// Search boost for the meanings fields. Meanings in the other languages are still
+    /// searched, but ranked lower.
+    pub fn meanings_boost(&self, path: &str) -> Option<f32> {
+        if path.starts_with("meanings") && path != self.meanings_path() {
+            Some(0.5)
+        } else {
+            None
+        }
+    }
+
+    /// Selects the UI string for the language. The UI is translated to german only, other
+    /// languages use the english UI.
+    pub fn tr<'a>(&self, en: &'a str, de: &'a str) -> &'a str {
+        if self.has_german_ui() {
+            de
+        } else {
+            en
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn lang_from_cookie() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Language::from_cookie(&headers), None);
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("theme=dark; lang=de"),
+        );
+        assert_eq!(
+            Language::from_cookie(&headers).map(|lang| lang.code()),
+            Some("de")
+        );
+    }
+}
@@ -1,9 +1,16 @@
 #![allow(non_snake_case)]
 use core::fmt;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use axum::{extract::Query, response::Html, routing::get, Router};
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap},
+    response::{AppendHeaders, Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
 use dioxus::prelude::*;
 //use search::run_search;
 use prettify_pinyin::prettify;
@@ -15,13 +22,26 @@ use serde::{de, Deserialize, Deserializer, Serialize};
 use measure_time::*;
 use tower_http::services::{ServeDir, ServeFile};
 
+mod kana;
+mod lang;
+mod ranking;
+mod rewrite;
 mod search;
+mod segment;
+mod separable;
 
 //use dioxus_router::{Route, Router};
 //use axum_server::tls_rustls::RustlsConfig;
 use urlencoding::encode;
 
-use crate::search::run_search_veloci;
+use crate::lang::Language;
+use crate::ranking::RankingMode;
+use crate::rewrite::{QueryRewrite, RewriteReason};
+use crate::search::{
+    find_homophones, preload, related_words, rewrite_query, run_search_veloci, segment_query,
+    RelatedPosition,
+};
+use veloci::result::SearchResultWithDoc;
 const APP_NAME: &str = "Chisho";
 
 //use syslog::{BasicLogger, Facility, Formatter3164};
@@ -41,6 +61,8 @@ async fn main() {
     //.map(|()| log::set_max_level(LevelFilter::Info))
     //.unwrap();
 
+    preload();
+
     let env_port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
     // parsed
     let port = env_port.parse::<u16>().unwrap_or(3000);
@@ -79,6 +101,7 @@ async fn main() {
                 //.route("/", get(app_ssr))
                 .route("/", get(app_endpoint))
                 .route("/about", get(app_endpoint))
+                .route("/chengyu", get(chengyu_endpoint))
                 .nest_service("/dist", serve_dir.clone())
                 .nest_service("/media", media_dir.clone())
                 .into_make_service(),
@@ -110,7 +133,7 @@ async fn main() {
 
 //object.onclick = function(){myScript};
 
-fn render_page(search_term: String, ssr_output: String) -> Html<String> {
+fn render_page(search_term: String, ssr_output: String, lang: Language) -> Html<String> {
     let title = if search_term.is_empty() {
         "Chisho.org - Chinese Dictionary".to_string()
     } else {
@@ -151,7 +174,7 @@ fn render_page(search_term: String, ssr_output: String) -> Html<String> {
     Html(format!(
         r#"
 <!DOCTYPE html>
-<html data-theme="emerald" lang="en">
+<html data-theme="emerald" lang="{lang}">
   <head>
     <link rel="icon" href="dist/favicon.svg"/>
     <link rel="stylesheet" href="/dist/output.css">
@@ -195,6 +218,12 @@ struct Params {
 
     #[serde(default, deserialize_with = "empty_string_as_none")]
     top: Option<usize>,
+
+    #[serde(default, deserialize_with = "invalid_as_none")]
+    lang: Option<Language>,
+
+    #[serde(default, deserialize_with = "invalid_as_none")]
+    mode: Option<RankingMode>,
 }
 
 /// Serde deserialization decorator to map empty Strings to None,
@@ -211,20 +240,89 @@ where
     }
 }
 
-async fn app_endpoint(params: Query<Params>) -> Html<String> {
+/// Serde deserialization decorator to map empty and unknown values to None, so a bad preference
+/// like `?lang=xx` falls back to the cookie or the default instead of rejecting the request
+fn invalid_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let opt = Option::<String>::deserialize(de)?;
+    match opt.as_deref() {
+        None | Some("") => Ok(None),
+        Some(s) => match FromStr::from_str(s) {
+            Ok(val) => Ok(Some(val)),
+            Err(e) => {
+                log::warn!("Ignoring query param: {}", e);
+                Ok(None)
+            }
+        },
+    }
+}
+
+async fn app_endpoint(params: Query<Params>, headers: HeaderMap) -> Response {
     let search_term = params
         .q
         .as_ref()
         .map(ToString::to_string)
         .unwrap_or_default();
+    let (lang, mode) = get_preferences(&params, &headers);
     debug_time!("Render Page Time");
-    render_page(
+    let page = render_page(
         search_term.to_string(),
         dioxus_ssr::render_lazy(rsx! {
-            Page{q: search_term, top: params.top.unwrap_or(20)}
+            Page{q: search_term, top: params.top.unwrap_or(20), lang: lang, mode: mode}
             //Page{q: params.q.as_ref().unwrap_or(&"".to_string()).to_string(), top: }
         }),
-    )
+        lang,
+    );
+    with_preference_cookies(&params, page)
+}
+
+/// Browse page for idioms, the query filters the idioms
+async fn chengyu_endpoint(params: Query<Params>, headers: HeaderMap) -> Response {
+    let filter = params
+        .q
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let (lang, mode) = get_preferences(&params, &headers);
+    let page = render_page(
+        "成語".to_string(),
+        dioxus_ssr::render_lazy(rsx! {
+            ChengyuPage{filter: filter, top: params.top.unwrap_or(50), lang: lang, mode: mode}
+        }),
+        lang,
+    );
+    with_preference_cookies(&params, page)
+}
+
+/// The query params override the preferences stored in the cookies
+fn get_preferences(params: &Params, headers: &HeaderMap) -> (Language, RankingMode) {
+    let lang = params
+        .lang
+        .or_else(|| Language::from_cookie(headers))
+        .unwrap_or_default();
+    let mode = params
+        .mode
+        .or_else(|| RankingMode::from_cookie(headers))
+        .unwrap_or_default();
+    (lang, mode)
+}
+
+/// Persist the preferences given as query params
+fn with_preference_cookies(params: &Params, page: Html<String>) -> Response {
+    let cookies = params
+        .lang
+        .map(|lang| (header::SET_COOKIE, lang.to_cookie()))
+        .into_iter()
+        .chain(
+            params
+                .mode
+                .map(|mode| (header::SET_COOKIE, mode.to_cookie())),
+        );
+    (AppendHeaders(cookies), page).into_response()
 }
 
 const LINK_CLASSES: &str = "underline text-slate-500 hover:text-blue-600 ";
@@ -244,19 +342,135 @@ pub struct Entry {
     pinyin_pretty: String,
     tocfl_level: Option<u32>,
     meanings: Vec<String>,
-    meanings_de: Vec<String>,
+    #[serde(default)]
+    examples: Vec<ExampleSentence>,
+    #[serde(default)]
+    japanese: Vec<JapaneseWord>,
+    #[serde(default)]
+    pos: Vec<PosShare>,
+    // Literal and figurative meanings of idioms
+    #[serde(default)]
+    literal_meanings: Vec<String>,
+    #[serde(default)]
+    figurative_meanings: Vec<String>,
+    // Most common reading and gloss of each character of words
+    #[serde(default)]
+    components: Vec<ComponentChar>,
     tags: Vec<String>,
     commonness_boost: f64,
     count_per_million_written: u64,
     count_per_million_spoken: u64,
     count_per_million_in_others: u64,
+    // Frequency on the Zipf scale, log10 of the frequency per billion words
+    #[serde(default)]
+    zipf: f64,
+    // Remaining fields, including the `meanings_{lang}` of the other languages
+    #[serde(flatten)]
+    other_fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl Entry {
+    /// The zipf value rounded to a band from 1 (rare) to 7 (very common)
+    fn frequency_band(&self) -> u32 {
+        self.zipf.round().clamp(1.0, 7.0) as u32
+    }
+
+    /// The meanings in the language. Falls back to english and then to the other languages, since
+    /// some entries exist only in one of the translation dictionaries.
+    fn meanings_in(&self, lang: Language) -> Vec<String> {
+        std::iter::once(lang)
+            .chain(Language::all().iter().copied())
+            .map(|lang| self.meanings_in_lang(lang))
+            .find(|meanings| !meanings.is_empty())
+            .unwrap_or_default()
+    }
+
+    fn meanings_in_lang(&self, lang: Language) -> Vec<String> {
+        if lang == Language::EN {
+            return self.meanings.clone();
+        }
+        self.other_fields
+            .get(&lang.meanings_field())
+            .and_then(|meanings| serde_json::from_value(meanings.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Part of speech with its share in SUBTLEX-CH
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PosShare {
+    pos: String,
+    share: f64,
+}
+
+/// Character of a word with its most common reading and english gloss
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ComponentChar {
+    traditional: String,
+    simplified: String,
+    pinyin_pretty: String,
+    meaning: String,
+}
+
+/// JMdict word with the same kanji spelling
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct JapaneseWord {
+    kanji: String,
+    readings: Vec<String>,
+    meanings: Vec<String>,
+    #[serde(default)]
+    meanings_de: Vec<String>,
+    common: bool,
+}
+
+impl JapaneseWord {
+    /// E.g. "勉強 (べんきょう) – study"
+    fn summary(&self, lang: Language) -> String {
+        let meanings = if lang.has_german_ui() && !self.meanings_de.is_empty() {
+            &self.meanings_de
+        } else {
+            &self.meanings
+        };
+        format!(
+            "{} ({}) – {}",
+            self.kanji,
+            self.readings.join("、"),
+            meanings.join(", ")
+        )
+    }
+}
+
+/// Example sentence from a translation dictionary, e.g. HanDeDict
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExampleSentence {
+    simplified: String,
+    traditional: String,
+    translation: String,
+    // Language code of the translation, e.g. "de"
+    lang: String,
 }
 
 fn Page(cx: Scope<Params>) -> Element {
     let term = cx.props.q.to_owned().unwrap_or("".to_string());
     let top = cx.props.top.to_owned().unwrap_or(20);
+    let lang = cx.props.lang.unwrap_or_default();
+    let mode = cx.props.mode.unwrap_or_default();
+    if let Some(word) = term.trim().strip_prefix(HOMOPHONES_PREFIX) {
+        return cx.render(rsx!(HomophonesPage {
+            query: term.to_string(),
+            word: word.to_string(),
+            top: top,
+            lang: lang,
+            mode: mode
+        }));
+    }
+    let (search_term, rewrites) = if !term.is_empty() {
+        rewrite_query(&term)
+    } else {
+        Default::default()
+    };
     let req = if !term.is_empty() {
-        let res = run_search_veloci(&term, top);
+        let res = run_search_veloci(&search_term, top, lang, mode);
         match res {
             Ok(res) => res,
             Err(e) => {
@@ -266,7 +480,8 @@ fn Page(cx: Scope<Params>) -> Element {
                     div{
                         class:"container mx-auto px-4 max-w-screen-md",
                         Logo{}
-                        SearchInput{input_value: term.to_string()}
+                        LanguageSwitch{current_query: term.to_string(), base_url: "/".to_string(), lang: lang, mode: mode}
+                        SearchInput{input_value: term.to_string(), lang: lang}
                         cx.render(rsx!(div { "{e}" }))
                     }
                 ));
@@ -276,32 +491,102 @@ fn Page(cx: Scope<Params>) -> Element {
         Default::default()
     };
 
-    let entries = req
-        .data
-        .iter()
-        .map(|hit| serde_json::from_str(&serde_json::to_string(&hit.doc).unwrap()).unwrap())
-        .collect::<Vec<_>>();
+    let entries = to_entries(&req);
     let has_query = !term.is_empty();
+    let segments = if has_query {
+        segment_query(&search_term)
+    } else {
+        Vec::new()
+    };
+    // Related words for the card of the searched word
+    let related = match entries.first() {
+        Some(entry) if entry.traditional == search_term || entry.simplified == search_term => {
+            related_words(&search_term, RELATED_WORDS_LIMIT)
+        }
+        _ => Vec::new(),
+    };
 
     cx.render(rsx!(
         div{
             class:"container mx-auto px-4 max-w-screen-md",
             Logo{}
-            SearchInput{input_value: term.to_string()}
+            LanguageSwitch{current_query: term.to_string(), base_url: "/".to_string(), lang: lang, mode: mode}
+            SearchInput{input_value: term.to_string(), lang: lang}
+            for rewrite in &rewrites {
+                RewriteNote{rewrite: rewrite.clone(), lang: lang}
+            }
+            for (segmented_term, parts) in &segments {
+                Breakdown{term: segmented_term.to_string(), parts: parts.clone()}
+            }
             if has_query{
                 cx.render(rsx! {
-                    SearchResult {entries: entries, num_results: req.num_hits, current_query: term, top: top},
+                    SearchResult {entries: entries, num_results: req.num_hits, current_query: term, base_url: "/".to_string(), top: top, lang: lang, related: related, tags_of_word: false},
                 })
             }
             if !has_query{
                 cx.render(rsx! {
-                    StartPage {}
+                    StartPage {lang: lang}
                 })
             }
         }
     ))
 }
 
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct RewriteNoteProps {
+    rewrite: QueryRewrite,
+    lang: Language,
+}
+
+/// Note for a query term which was searched as its base word, e.g. 研究 for 研究研究 or 吃 for 吃過
+pub fn RewriteNote(cx: Scope<RewriteNoteProps>) -> Element {
+    let rewrite = &cx.props.rewrite;
+    let lang = cx.props.lang;
+    let reason = match rewrite.reason {
+        RewriteReason::Reduplication(pattern) => {
+            format!(
+                ", {} {}",
+                pattern,
+                lang.tr("reduplication", "Reduplikation")
+            )
+        }
+        RewriteReason::Inflection => String::new(),
+    };
+    cx.render(rsx!(
+        div {
+            class: "text-sm text-slate-500 my-1",
+            "{lang.tr(\"Showing results for\", \"Ergebnisse für\")} "
+            a { href: "{get_search_url(&rewrite.base, \"\")}", class: "font-bold", "{rewrite.base}" }
+            " ({rewrite.original}{reason})"
+        }
+    ))
+}
+
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct BreakdownProps {
+    term: String,
+    parts: Vec<String>,
+}
+
+/// Segmentation of a query without an entry, e.g. 我想去台北 = 我 + 想 + 去 + 台北
+pub fn Breakdown(cx: Scope<BreakdownProps>) -> Element {
+    let parts = &cx.props.parts;
+    cx.render(rsx!(
+        div {
+            class: "text-lg my-2",
+            span { class: "font-bold", "{cx.props.term} = " }
+            for (i, part) in parts.iter().enumerate() {
+                if i > 0 {
+                    cx.render(rsx!(span { class: "text-slate-400", " + " }))
+                }
+                a { href: "{get_search_url(part, \"\")}", class: "hover:underline", "{part}" }
+            }
+        }
+    ))
+}
+
 pub fn Logo(cx: Scope) -> Element {
     cx.render(rsx!(
 
@@ -370,13 +655,74 @@ pub fn Logo(cx: Scope) -> Element {
     //))
 }
 
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct LanguageSwitchProps {
+    current_query: String,
+    // The page the links go to, e.g. "/" or "/chengyu"
+    base_url: String,
+    lang: Language,
+    mode: RankingMode,
+}
+
+/// Links to switch the language, the choice is stored in a cookie
+pub fn LanguageSwitch(cx: Scope<LanguageSwitchProps>) -> Element {
+    let q = &cx.props.current_query;
+    let base_url = &cx.props.base_url;
+    let links = Language::all()
+        .iter()
+        .map(|lang| {
+            let class = if *lang == cx.props.lang {
+                "font-bold"
+            } else {
+                ""
+            };
+            (
+                get_search_url_with_lang(base_url, q, *lang),
+                lang.name(),
+                class,
+            )
+        })
+        .collect::<Vec<_>>();
+    // Switch between the modern and the classical ranking
+    let lang = cx.props.lang;
+    let mode_url = get_search_url_with_mode(base_url, q, cx.props.mode.toggle());
+    let (mode_name, mode_title) = match cx.props.mode {
+        RankingMode::Modern => (
+            lang.tr("Classical mode", "Klassischer Modus"),
+            lang.tr(
+                "Rank characters by their frequency in classical texts",
+                "Schriftzeichen nach ihrer Häufigkeit in klassischen Texten sortieren",
+            ),
+        ),
+        RankingMode::Classical => (
+            lang.tr("Modern mode", "Moderner Modus"),
+            lang.tr(
+                "Rank by the frequency in modern chinese",
+                "Nach der Häufigkeit im modernen Chinesisch sortieren",
+            ),
+        ),
+    };
+    cx.render(rsx!(
+        div {
+            class: "text-sm text-right",
+            for (url, name, class) in links.iter() {
+                a { class: "pl-2 {LINK_CLASSES} {class}", href: "{url}", "{name}" }
+            }
+            a { class: "pl-4 {LINK_CLASSES}", href: "{mode_url}", title: "{mode_title}", "{mode_name}" }
+        }
+    ))
+}
+
 // Remember: Owned props must implement `PartialEq`!
 #[derive(PartialEq, Props)]
 pub struct InputParams {
     input_value: String,
+    lang: Language,
 }
 
 pub fn SearchInput(cx: Scope<InputParams>) -> Element {
+    let lang = cx.props.lang;
     cx.render(rsx!(
         div {
             class: "grow",
@@ -388,13 +734,13 @@ pub fn SearchInput(cx: Scope<InputParams>) -> Element {
                         class: "bg-transparent text-gray-700 w-full focus:outline-none focus:shadow-outline border border-gray-300 rounded-lg py-2 px-4 block appearance-none leading-normal",
                         value: "{cx.props.input_value}",
                         name: "q",
-                        placeholder: "Chinese, English, pinyin, zhuyin",
+                        placeholder: "{lang.tr(\"Chinese, English, pinyin, zhuyin\", \"Chinesisch, Deutsch, Englisch, Pinyin, Zhuyin\")}",
                         r#type: "text",
                         autofocus: true
                     }
                     button {
                         class: "btn",
-                        "Search"
+                        "{lang.tr(\"Search\", \"Suchen\")}"
                     }
                 }
             }
@@ -408,44 +754,128 @@ pub struct SearchResultProps {
     entries: Vec<Entry>,
     num_results: u64,
     current_query: String,
+    // The page the link to more results goes to, e.g. "/" or "/chengyu"
+    base_url: String,
     top: usize,
+    lang: Language,
+    // Related words of the first entry
+    related: Vec<(RelatedPosition, Vec<String>)>,
+    // Tag links search the word of the entry instead of the current query, e.g. on the homophones page
+    tags_of_word: bool,
 }
 
 pub fn SearchResult(cx: Scope<SearchResultProps>) -> Element {
     let new_top = cx.props.top + 20;
     let q = &cx.props.current_query;
+    let lang = cx.props.lang;
     cx.render(rsx!(
         div {
             class: "grow",
-            div { class:"text-sm text-slate-400", "{cx.props.num_results} Results" }
+            div { class:"text-sm text-slate-400", "{cx.props.num_results} {lang.tr(\"Results\", \"Ergebnisse\")}" }
 
             div {
                 class: "p-1",
                 ul {
-                    for entry in &cx.props.entries {
-                        li { SearchResultItem{entry:entry.clone(), current_query: cx.props.current_query.to_string()} }
+                    for (i, entry) in cx.props.entries.iter().enumerate() {
+                        li {
+                            SearchResultItem{entry:entry.clone(), current_query: cx.props.current_query.to_string(), lang: lang, tags_of_word: cx.props.tags_of_word}
+                            if i == 0 && !cx.props.related.is_empty() {
+                                cx.render(rsx! {
+                                    RelatedWords{related: cx.props.related.clone(), lang: lang}
+                                })
+                            }
+                        }
                     }
                 }
             }
             if (cx.props.entries.len() as u64) < cx.props.num_results {
                 cx.render(rsx! {
-                   a{ href:"{get_search_url_with_top(q, \"\", new_top)}",  "More Words >"}
+                   a{ href:"{get_search_url_with_top(&cx.props.base_url, q, \"\", new_top)}",  "{lang.tr(\"More Words >\", \"Mehr Wörter >\")}"}
                 })
             }
         }
     ))
 }
 
+const RELATED_WORDS_LIMIT: usize = 8;
+
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct RelatedWordsProps {
+    related: Vec<(RelatedPosition, Vec<String>)>,
+    lang: Language,
+}
+
+/// Common words starting with, ending with or containing the word of the card
+pub fn RelatedWords(cx: Scope<RelatedWordsProps>) -> Element {
+    let lang = cx.props.lang;
+    let label = |position: &RelatedPosition| match position {
+        RelatedPosition::Start => lang.tr("Starting with", "Beginnt mit"),
+        RelatedPosition::End => lang.tr("Ending with", "Endet mit"),
+        RelatedPosition::Middle => lang.tr("Containing", "Enthält"),
+    };
+    cx.render(rsx!(
+        div {
+            class: "text-sm mb-4 pl-2",
+            div { class: "font-bold text-slate-500", "{lang.tr(\"Related words\", \"Verwandte Wörter\")}" }
+            for (position, words) in cx.props.related.iter() {
+                div {
+                    span { class: "italic text-slate-500", "{label(position)}: " }
+                    for word in words.iter() {
+                        a { href: "{get_search_url(word, \"\")}", class: "mr-2 hover:underline", "{word}" }
+                    }
+                }
+            }
+        }
+    ))
+}
+
 // Remember: Owned props must implement `PartialEq`!
 #[derive(PartialEq, Props)]
 pub struct SearchResultItemProp {
     entry: Entry,
     current_query: String,
+    lang: Language,
+    tags_of_word: bool,
 }
 
 pub fn SearchResultItem(cx: Scope<SearchResultItemProp>) -> Element {
     let entry = &cx.props.entry;
-    let q = &cx.props.current_query;
+    let lang = cx.props.lang;
+    let q = if cx.props.tags_of_word {
+        &entry.traditional
+    } else {
+        &cx.props.current_query
+    };
+
+    let meanings = entry.meanings_in(lang);
+    // Idioms show the figurative meaning first. The split exists only for the english meanings.
+    let is_idiom = !entry.figurative_meanings.is_empty() && meanings == entry.meanings;
+    // E.g. "verb, noun"
+    let word_class = entry
+        .pos
+        .iter()
+        .filter(|pos| pos.share >= 0.1)
+        .map(|pos| pos.pos.replace('_', " "))
+        .collect::<Vec<_>>()
+        .join(", ");
+    // Show at most two japanese words, common words come first
+    let japanese = entry
+        .japanese
+        .iter()
+        .take(2)
+        .map(|word| word.summary(lang))
+        .collect::<Vec<_>>();
+    let frequency_band = entry.frequency_band();
+    let frequency_bars = (1..=7)
+        .map(|band| {
+            if band <= frequency_band {
+                "bg-primary"
+            } else {
+                "bg-base-300"
+            }
+        })
+        .collect::<Vec<_>>();
 
     //let audio_path = format!("../../")
 
@@ -518,6 +948,13 @@ pub fn SearchResultItem(cx: Scope<SearchResultItemProp>) -> Element {
 
     //<fg t="わたし">私</fg>
 
+    let homophones_url = get_search_url(
+        &format!(
+            "{}{} {}",
+            HOMOPHONES_PREFIX, entry.traditional, entry.pinyin
+        ),
+        "",
+    );
     let mut pinyin = entry.pinyin_pretty.to_string();
     let zhuyin = entry.zhuyin.to_string();
     if let Some(pinyin_taiwan) = entry.pinyin_taiwan.as_ref() {
@@ -549,18 +986,24 @@ pub fn SearchResultItem(cx: Scope<SearchResultItemProp>) -> Element {
                         p{
                             button {
                                 class: "attach_to_{audio.0} {LINK_CLASSES} text-sm",
-                                "Play Audio"
+                                "{lang.tr(\"Play Audio\", \"Audio abspielen\")}"
                             }
                         }
                     })
                 }
 
             }
-            progress {
-                class: "progress w-56",
-                max: "10", // Actual max is higher
-                value: "{entry.commonness_boost - 1.0}",
+            div {
+                class: "flex items-center gap-1",
+                title: "{lang.tr(\"Zipf frequency\", \"Zipf-Frequenz\")} {entry.zipf:.1}",
+                for bar in frequency_bars.iter() {
+                    cx.render(rsx! {
+                        span { class: "w-4 h-2 rounded {bar}" }
+                    })
+                }
+                span { class: "text-sm pl-1", "{lang.tr(\"Frequency\", \"Häufigkeit\")} {frequency_band}/7" }
             }
+            a { class: "text-sm {LINK_CLASSES}", href: "{homophones_url}", "{lang.tr(\"Homophones\", \"Homophone\")}" }
             div{
                 for tag in entry.tags.iter().filter(|tag|tag.as_str() != "TOCFL") {
                     cx.render(rsx! {
@@ -572,13 +1015,62 @@ pub fn SearchResultItem(cx: Scope<SearchResultItemProp>) -> Element {
             }
             },
             div { class:"basis-3/4 pl-1",
-                for (i, def) in entry.meanings.iter().enumerate() {
-                    div { "{i+1}. {def}" }
+                if !word_class.is_empty() {
+                    cx.render(rsx! {
+                        div { class: "text-sm italic text-slate-500", "{word_class}" }
+                    })
+                }
+                if is_idiom {
+                    cx.render(rsx! {
+                        for def in entry.figurative_meanings.iter() {
+                            div { "{def}" }
+                        }
+                        for def in entry.literal_meanings.iter() {
+                            div { class: "text-sm text-slate-500",
+                                span { class: "italic", "{lang.tr(\"Literally\", \"Wörtlich\")}: " }
+                                "{def}"
+                            }
+                        }
+                    })
+                }
+                if !is_idiom {
+                    cx.render(rsx! {
+                        for (i, def) in meanings.iter().enumerate() {
+                            div { "{i+1}. {def}" }
+                        }
+                    })
+                }
+                if !entry.components.is_empty() {
+                    cx.render(rsx! {
+                        div { class: "mt-1 text-sm",
+                            for (i, component) in entry.components.iter().enumerate() {
+                                if i > 0 {
+                                    cx.render(rsx!(span { class: "text-slate-400", " + " }))
+                                }
+                                a { href: "{get_search_url(&component.traditional, \"\")}", class: "font-medium hover:underline", "{component.traditional}" }
+                                span { class: "text-slate-500", " {component.pinyin_pretty} \"{component.meaning}\"" }
+                            }
+                        }
+                    })
+                }
+                for japanese_word in japanese.iter() {
+                    div { class: "mt-1 text-sm",
+                        span { class: "text-slate-500", "{lang.tr(\"Japanese\", \"Japanisch\")}: " }
+                        "{japanese_word}"
+                    }
+                }
+                for example in entry.examples.iter() {
+                    div { class: "mt-1 text-sm",
+                        span { class: "font-medium", "{example.traditional}" }
+                        if example.simplified != example.traditional {
+                            cx.render(rsx! {
+                                span { class: "text-slate-500", " 〔{example.simplified}〕" }
+                            })
+                        }
+                        span { class: "text-slate-500", lang: "{example.lang}", " – {example.translation}" }
+                    }
                 }
                     br { }
-                //for (i, def) in entry.meanings_de.iter().enumerate() {
-                    //div { "{i+1}. {def}" }
-                //}
             },
         }
         div { class: "divider" }
@@ -600,8 +1092,16 @@ fn get_search_url(q: &str, tag: &str) -> String {
     format!("/?q={}+{}", encode(q), encode(tag))
 }
 
-fn get_search_url_with_top(q: &str, tag: &str, top: usize) -> String {
-    format!("/?q={}+{}&top={}", encode(q), encode(tag), top)
+fn get_search_url_with_top(base_url: &str, q: &str, tag: &str, top: usize) -> String {
+    format!("{}?q={}+{}&top={}", base_url, encode(q), encode(tag), top)
+}
+
+fn get_search_url_with_lang(base_url: &str, q: &str, lang: Language) -> String {
+    format!("{}?q={}&lang={}", base_url, encode(q), lang)
+}
+
+fn get_search_url_with_mode(base_url: &str, q: &str, mode: RankingMode) -> String {
+    format!("{}?q={}&mode={}", base_url, encode(q), mode)
 }
 
 struct Example {
@@ -624,26 +1124,73 @@ impl Example {
         }
     }
 }
-pub fn StartPage(cx: Scope) -> Element {
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct StartPageProps {
+    lang: Language,
+}
+
+pub fn StartPage(cx: Scope<StartPageProps>) -> Element {
+    let lang = cx.props.lang;
+    let (search_desc, search_example) = if lang.has_german_ui() {
+        ("Suche auf Deutsch: ", "Familie")
+    } else {
+        ("Great English search: ", "home")
+    };
     let examples = vec![
         Example::new(
-            "Great English search: ",
-            &get_search_url("home", ""),
-            "home",
+            search_desc,
+            &get_search_url(search_example, ""),
+            search_example,
+        ),
+        Example::new(
+            lang.tr("TOCFL words: ", "TOCFL-Wörter: "),
+            &get_search_url("", "#TOCFL1"),
+            "#TOCFL1",
         ),
-        Example::new("TOCFL words: ", &get_search_url("", "#TOCFL1"), "#TOCFL1"),
         Example::new(
-            "Filter for common words: ",
+            lang.tr("Filter for common words: ", "Nur häufige Wörter: "),
             &get_search_url("", "#common"),
             "#common",
         ),
         Example::new(
-            "Mix chinese and pinyin: ",
+            lang.tr("Browse idioms: ", "Redewendungen durchsuchen: "),
+            "/chengyu",
+            "成語",
+        ),
+        Example::new(
+            lang.tr("Find homophones: ", "Homophone finden: "),
+            &get_search_url("homophones:shi4", ""),
+            "homophones:shi4",
+        ),
+        Example::new(
+            lang.tr("Tone pairs: ", "Tonpaare: "),
+            &get_search_url("tones:3-4", "#TOCFL1"),
+            "tones:3-4 #TOCFL1",
+        ),
+        Example::new(
+            lang.tr("Rhymes: ", "Reime: "),
+            &get_search_url("rhyme:ang", "#common"),
+            "rhyme:ang #common",
+        ),
+        Example::new(
+            lang.tr("Filter by word class: ", "Nach Wortart filtern: "),
+            &get_search_url("", "#measure_word"),
+            "#measure_word",
+        ),
+        Example::new(
+            lang.tr(
+                "Mix chinese and pinyin: ",
+                "Chinesisch und Pinyin mischen: ",
+            ),
             &get_search_url("分 xiang3", ""),
             "分 xiang3",
         ),
         Example::new_multi(
-            "Different variants of pinyin: ",
+            lang.tr(
+                "Different variants of pinyin: ",
+                "Verschiedene Pinyin-Schreibweisen: ",
+            ),
             vec![
                 (get_search_url("xiawu", ""), "xiawu".to_string()),
                 (get_search_url("xia wu", ""), "xia wu".to_string()),
@@ -652,28 +1199,39 @@ pub fn StartPage(cx: Scope) -> Element {
             ],
         ),
         Example::new_multi(
-            "Search with zhuyin: ",
+            lang.tr("Search with zhuyin: ", "Suche mit Zhuyin: "),
             vec![(get_search_url("ㄒㄧㄚˋ ㄨˇ", ""), "ㄒㄧㄚˋ ㄨˇ".to_string())],
         ),
     ];
 
+    let intro = if lang.has_german_ui() {
+        format!("{APP_NAME} ist ein leistungsstarkes Chinesisch-Deutsch-Wörterbuch. Es findet Wörter, chinesische Schriftzeichen, Pinyin und Zhuyin schnell und einfach. Wie Pleco, aber im Web, oder Jisho für Chinesisch.")
+    } else {
+        format!("{APP_NAME} is a powerful Chinese-English dictionary. It lets you find words, chinese characters, pinyin, zhuyin quickly and easily. It's like pleco but for the web, or Jisho for Chinese.")
+    };
+    let examples_intro = if lang.has_german_ui() {
+        format!("Hier sind einige Beispiele, was {APP_NAME} kann")
+    } else {
+        format!("Here are some examples on what {APP_NAME} can do")
+    };
+
     cx.render(rsx!(div{
         class: "m-4 flex justify-center leading-loose",
         div{
             class: "max-w-lg",
             p {
                 class: "",
-                "{APP_NAME} is a powerful Chinese-English dictionary. It lets you find words, chinese characters, pinyin, zhuyin quickly and easily. It's like pleco but for the web, or Jisho for Chinese."
+                "{intro}"
             }
             p {
                 class: "mt-4",
-                "Here are some examples on what {APP_NAME} can do"
+                "{examples_intro}"
             }
 
             ul{ class: "mt-2 list-disc list-inside",
                 for example in examples.iter() {
                     cx.render(rsx! {
-                        li{  "{example.desc} ", 
+                        li{  "{example.desc} ",
 
                             for el in example.examples.iter() {
                                 cx.render(rsx! {
@@ -693,6 +1251,117 @@ pub fn StartPage(cx: Scope) -> Element {
     }))
 }
 
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct ChengyuPageProps {
+    filter: String,
+    top: usize,
+    lang: Language,
+    mode: RankingMode,
+}
+
+/// Path of the idiom browse page
+const CHENGYU_URL: &str = "/chengyu";
+
+/// Lists the idioms (#chengyu), most common first. The filter searches within the idioms.
+pub fn ChengyuPage(cx: Scope<ChengyuPageProps>) -> Element {
+    let lang = cx.props.lang;
+    let query = format!("#chengyu {}", cx.props.filter);
+    let (entries, num_results) = match run_search_veloci(&query, cx.props.top, lang, cx.props.mode)
+    {
+        Ok(res) => (to_entries(&res), res.num_hits),
+        Err(e) => {
+            error!("Error in search: {:?}", e);
+            (Vec::new(), 0)
+        }
+    };
+    cx.render(rsx!(
+        div{
+            class:"container mx-auto px-4 max-w-screen-md",
+            Logo{}
+            LanguageSwitch{current_query: cx.props.filter.to_string(), base_url: CHENGYU_URL.to_string(), lang: lang, mode: cx.props.mode}
+            h1 { class: "text-2xl font-medium mt-4", "Chengyu (成語)" }
+            p { class: "mt-2 mb-4",
+                "{lang.tr(\"Four-character idioms, the most common first. Idioms are also found in the regular search with the tag #chengyu.\", \"Redewendungen aus vier Schriftzeichen, die häufigsten zuerst. Sie werden auch in der normalen Suche mit dem Tag #chengyu gefunden.\")}"
+            }
+            form {
+                action: CHENGYU_URL,
+                div {
+                    class: "flex mx-auto mb-4",
+                    input {
+                        class: "bg-transparent text-gray-700 w-full focus:outline-none focus:shadow-outline border border-gray-300 rounded-lg py-2 px-4 block appearance-none leading-normal",
+                        value: "{cx.props.filter}",
+                        name: "q",
+                        placeholder: "{lang.tr(\"Filter idioms, e.g. snake\", \"Redewendungen filtern, z.B. Schlange\")}",
+                        r#type: "text",
+                    }
+                    button {
+                        class: "btn",
+                        "{lang.tr(\"Filter\", \"Filtern\")}"
+                    }
+                }
+            }
+            SearchResult {entries: entries, num_results: num_results, current_query: cx.props.filter.to_string(), base_url: CHENGYU_URL.to_string(), top: cx.props.top, lang: lang, related: Vec::new(), tags_of_word: false},
+        }
+    ))
+}
+
+/// Query prefix for the homophones of a word or pinyin, e.g. "homophones:電腦" or
+/// "homophones:行 hang2" for one reading
+const HOMOPHONES_PREFIX: &str = "homophones:";
+
+// Remember: Owned props must implement `PartialEq`!
+#[derive(PartialEq, Props)]
+pub struct HomophonesPageProps {
+    query: String,
+    word: String,
+    top: usize,
+    lang: Language,
+    mode: RankingMode,
+}
+
+/// Lists the homophones (same pinyin and tones) and near-homophones (different tones) of a word,
+/// the most common first
+pub fn HomophonesPage(cx: Scope<HomophonesPageProps>) -> Element {
+    let lang = cx.props.lang;
+    let query = &cx.props.query;
+    let homophones = match find_homophones(&cx.props.word, cx.props.top) {
+        Ok(homophones) => homophones,
+        Err(e) => {
+            error!("Error in homophone search: {:?}", e);
+            return cx.render(rsx!(
+                div{
+                    class:"container mx-auto px-4 max-w-screen-md",
+                    Logo{}
+                    LanguageSwitch{current_query: query.to_string(), base_url: "/".to_string(), lang: lang, mode: cx.props.mode}
+                    SearchInput{input_value: query.to_string(), lang: lang}
+                    div { "{e}" }
+                }
+            ));
+        }
+    };
+    let pinyin = prettify(homophones.pinyin.to_string());
+    cx.render(rsx!(
+        div{
+            class:"container mx-auto px-4 max-w-screen-md",
+            Logo{}
+            LanguageSwitch{current_query: query.to_string(), base_url: "/".to_string(), lang: lang, mode: cx.props.mode}
+            SearchInput{input_value: query.to_string(), lang: lang}
+            h2 { class: "text-xl font-medium mt-4", "{lang.tr(\"Homophones of\", \"Homophone von\")} {cx.props.word} ({pinyin})" }
+            SearchResult {entries: to_entries(&homophones.exact), num_results: homophones.exact.num_hits, current_query: query.to_string(), base_url: "/".to_string(), top: cx.props.top, lang: lang, related: Vec::new(), tags_of_word: true},
+            h2 { class: "text-xl font-medium mt-4", "{lang.tr(\"Near-homophones (other tones)\", \"Fast-Homophone (andere Töne)\")}" }
+            SearchResult {entries: to_entries(&homophones.near), num_results: homophones.near.num_hits, current_query: query.to_string(), base_url: "/".to_string(), top: cx.props.top, lang: lang, related: Vec::new(), tags_of_word: true},
+        }
+    ))
+}
+
+fn to_entries(res: &SearchResultWithDoc) -> Vec<Entry> {
+    res.data
+        .iter()
+        .map(|hit| serde_json::from_str(&serde_json::to_string(&hit.doc).unwrap()).unwrap())
+        .collect()
+}
+
 pub fn Footer(cx: Scope) -> Element {
     cx.render(rsx!(p {}))
 }
@@ -0,0 +1,99 @@
+use core::fmt;
+use std::str::FromStr;
+
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::lang::cookie_value;
+
+/// Name of the cookie which stores the ranking preference
+pub const MODE_COOKIE: &str = "mode";
+
+/// The ranking preference of the user.
+///
+/// The classical mode ranks characters by their frequency in classical texts in addition to
+/// the modern frequency, for readers of 古文 and 成語, where e.g. 之 and 乎 are very common.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RankingMode {
+    #[default]
+    Modern,
+    Classical,
+}
+
+impl FromStr for RankingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "modern" => Ok(RankingMode::Modern),
+            "classical" => Ok(RankingMode::Classical),
+            other => Err(format!("Unknown ranking mode {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for RankingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl RankingMode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RankingMode::Modern => "modern",
+            RankingMode::Classical => "classical",
+        }
+    }
+
+    /// The other mode, used for the switch
+    pub fn toggle(&self) -> RankingMode {
+        match self {
+            RankingMode::Modern => RankingMode::Classical,
+            RankingMode::Classical => RankingMode::Modern,
+        }
+    }
+
+    /// Reads the ranking preference from the `mode` cookie
+    pub fn from_cookie(headers: &HeaderMap) -> Option<RankingMode> {
+        cookie_value(headers, MODE_COOKIE).and_then(|value| value.parse().ok())
+    }
+
+    /// The `Set-Cookie` header value to persist the ranking preference for a year
+    pub fn to_cookie(self) -> String {
+        format!(
+            "{}={}; Path=/; Max-Age=31536000; SameSite=Lax",
+            MODE_COOKIE,
+            self.code()
+        )
+    }
+
+    /// Additional boost field of the mode, added to `commonness_boost`
+    pub fn boost_path(&self) -> Option<&'static str> {
+        match self {
+            RankingMode::Modern => None,
+            RankingMode::Classical => Some("classical_boost"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header, HeaderValue};
+
+    use super::*;
+
+    #[test]
+    fn mode_from_cookie() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RankingMode::from_cookie(&headers), None);
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("lang=de; mode=classical"),
+        );
+        assert_eq!(
+            RankingMode::from_cookie(&headers),
+            Some(RankingMode::Classical)
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Rewrites of chinese query terms, which have no entry in the dictionary, to their base word.
+//! E.g. the reduplicated form 研究研究 is searched as 研究 and the inflected form 沒吃過 as 吃.
+
+/// A query term which was replaced by its base word, shown as a note above the results
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRewrite {
+    pub original: String,
+    pub base: String,
+    pub reason: RewriteReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RewriteReason {
+    /// E.g. "AABB" for 高高興興
+    Reduplication(&'static str),
+    /// Aspect particles, result complements or negation, e.g. 吃過
+    Inflection,
+}
+
+/// Aspect and result markers, in traditional and simplified
+const SUFFIXES: [char; 7] = ['了', '過', '过', '著', '着', '完', '好'];
+/// Negations, in traditional and simplified
+const PREFIXES: [char; 3] = ['不', '沒', '没'];
+
+/// Returns the base word of a reduplicated form and the pattern, e.g. 看看 -> (看, AA).
+///
+/// Patterns: AA (看看), ABAB (研究研究), AABB (高高興興) and A一A (想一想)
+pub fn reduplication_base(term: &str) -> Option<(String, &'static str)> {
+    let chars: Vec<char> = term.chars().collect();
+    match chars.as_slice() {
+        [a1, a2] if a1 == a2 => Some((a1.to_string(), "AA")),
+        [a1, '一', a2] if a1 == a2 => Some((a1.to_string(), "A一A")),
+        [a1, a2, b1, b2] if a1 == a2 && b1 == b2 && a1 != b1 => {
+            Some((format!("{}{}", a1, b1), "AABB"))
+        }
+        [a1, b1, a2, b2] if a1 == a2 && b1 == b2 && a1 != b1 => {
+            Some((format!("{}{}", a1, b1), "ABAB"))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the stem of an inflected form, e.g. 吃 for 吃過, 沒吃過 or 吃完了.
+/// At least one character of the term is kept.
+pub fn inflection_stem(term: &str) -> Option<String> {
+    let mut chars: Vec<char> = term.chars().collect();
+    let len = chars.len();
+    while chars.len() > 1 && SUFFIXES.contains(chars.last().unwrap()) {
+        chars.pop();
+    }
+    while chars.len() > 1 && PREFIXES.contains(&chars[0]) {
+        chars.remove(0);
+    }
+    if chars.len() == len {
+        return None;
+    }
+    Some(chars.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduplication_base() {
+        assert_eq!(reduplication_base("看看"), Some(("看".to_string(), "AA")));
+        assert_eq!(
+            reduplication_base("研究研究"),
+            Some(("研究".to_string(), "ABAB"))
+        );
+        assert_eq!(
+            reduplication_base("高高興興"),
+            Some(("高興".to_string(), "AABB"))
+        );
+        assert_eq!(
+            reduplication_base("想一想"),
+            Some(("想".to_string(), "A一A"))
+        );
+        assert_eq!(reduplication_base("一一"), Some(("一".to_string(), "AA")));
+        assert_eq!(reduplication_base("高興"), None);
+    }
+
+    #[test]
+    fn test_inflection_stem() {
+        assert_eq!(inflection_stem("吃過"), Some("吃".to_string()));
+        assert_eq!(inflection_stem("沒吃过"), Some("吃".to_string()));
+        assert_eq!(inflection_stem("吃完了"), Some("吃".to_string()));
+        assert_eq!(inflection_stem("不去"), Some("去".to_string()));
+        assert_eq!(inflection_stem("了"), None);
+        assert_eq!(inflection_stem("吃飯"), None);
+    }
+}
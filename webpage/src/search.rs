@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use fnv::FnvHashSet;
 use measure_time::*;
+use serde::Deserialize;
 
 use veloci::{
     error::VelociError,
@@ -12,12 +13,19 @@ use veloci::{
 };
 
 //pub const COMMON_CJK: [u32; 2] = [0x4E00, 0x9FFF];
-fn is_chinese(cha: char) -> bool {
+pub fn is_chinese(cha: char) -> bool {
     matches!(cha as u32, 0x4E00..=0x9FFF)
 }
 
 use regex::Regex;
 
+use crate::kana;
+use crate::lang::Language;
+use crate::ranking::RankingMode;
+use crate::rewrite::{self, QueryRewrite, RewriteReason};
+use crate::segment;
+use crate::separable;
+
 fn extract_hashtags(text: &str) -> Vec<String> {
     let HASHTAG_REGEX: Regex = Regex::new(r"\#[a-zA-Z][0-9a-zA-Z_]*").unwrap();
 
@@ -69,6 +77,79 @@ fn get_tag_filter(query: &mut String) -> Result<Option<SearchRequest>, VelociErr
     }
 }
 
+/// Prefix for tone patterns, e.g. "tones:3-4" (or "tones:34") for words with a 3rd and 4th tone
+const TONES_PREFIX: &str = "tones:";
+/// Prefix for rhymes, e.g. "rhyme:ang" for words ending in -ang, -iang or -uang
+const RHYME_PREFIX: &str = "rhyme:";
+/// Medials, which may precede the rhyme in the final of the last syllable
+const RHYME_MEDIALS: &str = "(i|u|ü)?";
+
+// Returns searches on the tone pattern and rhyme
+// Removes them from query
+fn get_syllable_filters(query: &mut String) -> Result<Vec<SearchRequest>, VelociError> {
+    let mut filters = Vec::new();
+    let mut terms = Vec::new();
+    for term in query.split_whitespace() {
+        let (path, value, is_regex) = if let Some(tones) = term.strip_prefix(TONES_PREFIX) {
+            let tones: String = tones.chars().filter(|cha| *cha != '-').collect();
+            if tones.is_empty() || !tones.chars().all(|cha| ('1'..='5').contains(&cha)) {
+                return Err(VelociError::InvalidRequest {
+                    message: "Tones must be 1 to 5 (5 is the neutral tone), e.g. tones:3-4"
+                        .to_string(),
+                });
+            }
+            ("tone_pattern", tones, false)
+        } else if let Some(rhyme) = term.strip_prefix(RHYME_PREFIX) {
+            let rhyme = rhyme.to_lowercase().replace("u:", "ü").replace('v', "ü");
+            // The apical vowel of zhi, shi, zi etc. is indexed as "-i"
+            if rhyme.is_empty()
+                || !rhyme
+                    .chars()
+                    .all(|cha| cha.is_ascii_lowercase() || cha == 'ü' || cha == '-')
+            {
+                return Err(VelociError::InvalidRequest {
+                    message: "Rhymes must be pinyin finals, e.g. rhyme:ang or rhyme:-i".to_string(),
+                });
+            }
+            // The rhyme is the end of the final, e.g. "ang" matches "iang" and "uang"
+            ("rhyme", format!("{}{}", RHYME_MEDIALS, rhyme), true)
+        } else {
+            terms.push(term);
+            continue;
+        };
+        filters.push(SearchRequest::Search(RequestSearchPart {
+            terms: vec![value],
+            path: path.to_string(),
+            is_regex,
+            ..Default::default()
+        }));
+    }
+    *query = terms.join(" ");
+    Ok(filters)
+}
+
+/// Prefixes for explicit japanese readings, e.g. "romaji:gaku" or "kana:がく"
+const KANA_PREFIXES: [&str; 2] = ["romaji:", "kana:"];
+
+// Returns the explicit japanese readings in hiragana
+// Removes them from query
+fn get_explicit_kana(query: &mut String) -> Vec<String> {
+    let mut readings = Vec::new();
+    let mut terms = Vec::new();
+    for term in query.split_whitespace() {
+        let reading = KANA_PREFIXES
+            .iter()
+            .find_map(|prefix| term.strip_prefix(prefix));
+        match reading {
+            Some(reading) if kana::is_kana(reading) => readings.push(kana::to_hiragana(reading)),
+            Some(reading) => readings.extend(kana::romaji_to_hiragana(reading)),
+            None => terms.push(term),
+        }
+    }
+    *query = terms.join(" ");
+    readings
+}
+
 use once_cell::sync::Lazy;
 static PERSISTENCE: Lazy<Persistence> = Lazy::new(|| {
     persistence::Persistence::load(PathBuf::from("../create_db/indices/dict_velo")).unwrap()
@@ -81,7 +162,362 @@ fn to_traditional_chinese_variant(kanji: char) -> char {
         .unwrap_or(kanji)
 }
 
-pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc, VelociError> {
+/// Returns true if the field is in the index. E.g. `meanings_fr[]` is missing if CFDICT was not
+/// in the build, and searching a missing field fails.
+pub fn has_field(path: &str) -> bool {
+    let requesto = search::Request {
+        search_req: Some(SearchRequest::Search(RequestSearchPart {
+            terms: vec!["a".to_string()],
+            path: path.to_string(),
+            ..Default::default()
+        })),
+        top: Some(1),
+        ..Default::default()
+    };
+    search::search(requesto, &PERSISTENCE).is_ok()
+}
+
+/// Fields from optional data of `create_json`, which are missing in the index if the data was not
+/// in the build: the examples of HanDeDict, the japanese readings of the kanji and the HSK levels
+const OPTIONAL_FIELDS: [&str; 5] = [
+    "examples[].translation",
+    "examples[].traditional",
+    "examples[].simplified",
+    "kana_search[]",
+    "hsk_level",
+];
+
+static AVAILABLE_OPTIONAL_FIELDS: Lazy<FnvHashSet<&'static str>> = Lazy::new(|| {
+    OPTIONAL_FIELDS
+        .iter()
+        .copied()
+        .filter(|path| has_field(path))
+        .collect()
+});
+
+/// Returns true if the field is always in the index or an optional field in this build
+fn is_searchable(path: &str) -> bool {
+    !OPTIONAL_FIELDS.contains(&path) || AVAILABLE_OPTIONAL_FIELDS.contains(path)
+}
+
+/// Returns true if the word is an english meaning or pinyin, e.g. "home" or "xiang"
+fn is_known_word(pers: &Persistence, word: &str) -> bool {
+    let word = word.to_lowercase();
+    ["meanings[]", "pinyin_search[]"].iter().any(|path| {
+        search_by_commonness(pers, path, &word, false, 1)
+            .map(|res| res.num_hits > 0)
+            .unwrap_or(false)
+    })
+}
+
+/// The `db.json` the index was built from
+const DB_PATH: &str = "../create_db/create_json/db.json";
+
+#[derive(Deserialize)]
+struct Headword {
+    traditional: String,
+    simplified: String,
+}
+
+/// Traditional and simplified headwords of all entries, to look up words without a search, e.g.
+/// for the segmentation. None if `db.json` is missing, then the index is searched.
+static HEADWORDS: Lazy<Option<FnvHashSet<String>>> = Lazy::new(|| {
+    let text = match std::fs::read_to_string(DB_PATH) {
+        Ok(text) => text,
+        Err(e) => {
+            log::warn!("Could not read {}: {}", DB_PATH, e);
+            return None;
+        }
+    };
+    let mut headwords = FnvHashSet::default();
+    let mut num_invalid = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str::<Headword>(line) {
+            Ok(headword) => {
+                headwords.insert(headword.traditional);
+                headwords.insert(headword.simplified);
+            }
+            Err(_) => num_invalid += 1,
+        }
+    }
+    if num_invalid > 0 {
+        log::warn!("Skipped {} invalid entries in {}", num_invalid, DB_PATH);
+    }
+    Some(headwords)
+});
+
+/// Loads the index and the headwords, so the first request doesn't wait for them
+pub fn preload() {
+    Lazy::force(&PERSISTENCE);
+    Lazy::force(&HEADWORDS);
+}
+
+/// Returns true if there is an entry with the word as headword
+fn has_headword(pers: &Persistence, word: &str) -> bool {
+    if let Some(headwords) = HEADWORDS.as_ref() {
+        return headwords.contains(word);
+    }
+    let word: String = word.chars().map(to_traditional_chinese_variant).collect();
+    match search_by_commonness(pers, "traditional", &word, false, 1) {
+        Ok(res) => res.num_hits > 0,
+        Err(e) => {
+            error!("Error in headword search: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Replaces chinese terms without an entry by their base word, e.g. 研究研究 by 研究 or 吃過 by
+/// 吃. Otherwise the regex query `.*吃過.*` matches unrelated compounds before the verb.
+/// Returns the new query and the applied rewrites, which are shown as a note.
+pub fn rewrite_query(query: &str) -> (String, Vec<QueryRewrite>) {
+    let pers = &PERSISTENCE;
+    let mut rewrites = Vec::new();
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| {
+            if !term.chars().all(is_chinese) || has_headword(pers, term) {
+                return term.to_string();
+            }
+            if let Some((base, pattern)) = rewrite::reduplication_base(term) {
+                rewrites.push(QueryRewrite {
+                    original: term.to_string(),
+                    base: base.clone(),
+                    reason: RewriteReason::Reduplication(pattern),
+                });
+                return base;
+            }
+            if let Some(stem) = rewrite::inflection_stem(term) {
+                if has_headword(pers, &stem) {
+                    rewrites.push(QueryRewrite {
+                        original: term.to_string(),
+                        base: stem.clone(),
+                        reason: RewriteReason::Inflection,
+                    });
+                    return stem;
+                }
+            }
+            term.to_string()
+        })
+        .collect();
+    (terms.join(" "), rewrites)
+}
+
+/// Segments chinese terms without an entry into dictionary words, e.g. 我想去台北 into
+/// 我 + 想 + 去 + 台北. Returns the terms with their parts.
+pub fn segment_query(query: &str) -> Vec<(String, Vec<String>)> {
+    let pers = &PERSISTENCE;
+    if query.len() > 80 {
+        return Vec::new();
+    }
+    query
+        .split_whitespace()
+        .filter(|term| term.chars().count() > 1 && term.chars().all(is_chinese))
+        .filter(|term| !has_headword(pers, term))
+        .map(|term| {
+            let parts = segment::segment(term, |word| has_headword(pers, word));
+            (term.to_string(), parts)
+        })
+        .filter(|(_, parts)| parts.len() > 1)
+        .collect()
+}
+
+/// Searches the term (a regex if `is_regex`) in one field, ranked by commonness
+fn search_by_commonness(
+    pers: &Persistence,
+    path: &str,
+    term: &str,
+    is_regex: bool,
+    top: usize,
+) -> Result<SearchResultWithDoc, VelociError> {
+    let requesto = search::Request {
+        search_req: Some(SearchRequest::Search(RequestSearchPart {
+            terms: vec![term.to_string()],
+            path: path.to_string(),
+            is_regex,
+            ..Default::default()
+        })),
+        boost: Some(vec![RequestBoostPart {
+            path: "commonness_boost".to_string(),
+            boost_fun: Some(search::BoostFunction::Add),
+            ..Default::default()
+        }]),
+        top: Some(top),
+        ..Default::default()
+    };
+    let res = search::search(requesto.clone(), pers)?;
+    Ok(search::to_search_result(pers, res, &requesto.select))
+}
+
+/// Homophones of a word, ranked by commonness
+#[derive(Debug, Default)]
+pub struct Homophones {
+    /// E.g. "dian4nao3"
+    pub pinyin: String,
+    /// Same pinyin and tones
+    pub exact: SearchResultWithDoc,
+    /// Same pinyin with different tones
+    pub near: SearchResultWithDoc,
+}
+
+/// Finds the homophones of a word (e.g. 電腦, using its most common reading), of pinyin with
+/// tone numbers (e.g. dian4nao3 or "dian4 nao3") or of a word with one of its readings (e.g.
+/// "行 hang2"). The word itself is not listed.
+pub fn find_homophones(query: &str, top: usize) -> Result<Homophones, VelociError> {
+    let pers = &PERSISTENCE;
+    let word: String = query
+        .chars()
+        .filter(|cha| is_chinese(*cha))
+        .map(to_traditional_chinese_variant)
+        .collect();
+    let word = (!word.is_empty()).then_some(word);
+    let pinyin: String = query
+        .to_lowercase()
+        .chars()
+        .filter(|cha| !cha.is_whitespace() && !is_chinese(*cha))
+        .collect();
+    let pinyin = if pinyin.chars().any(|cha| cha.is_ascii_digit()) {
+        Some(pinyin)
+    } else if let Some(word) = word.as_ref() {
+        let res = search_by_commonness(pers, "traditional", word, false, 1)?;
+        res.data
+            .first()
+            .and_then(|hit| hit.doc["pinyin_compact"].as_str())
+            .map(str::to_string)
+    } else {
+        None
+    };
+    let Some(pinyin) = pinyin else {
+        return Err(VelociError::InvalidRequest {
+            message: "Homophones need a word or pinyin with tone numbers, e.g. dian4nao3"
+                .to_string(),
+        });
+    };
+    let toneless: String = pinyin.chars().filter(|cha| !cha.is_ascii_digit()).collect();
+
+    let is_other_word = |doc: &serde_json::Value| word.as_deref() != doc["traditional"].as_str();
+    // Readings of the word itself, which are excluded from both lists and their counts
+    let own_readings: Vec<String> = match word.as_ref() {
+        Some(word) => search_by_commonness(pers, "traditional", word, false, 20)?
+            .data
+            .iter()
+            .filter(|hit| !is_other_word(&hit.doc))
+            .filter_map(|hit| hit.doc["pinyin_compact"].as_str().map(str::to_string))
+            .collect(),
+        None => Vec::new(),
+    };
+    let num_own_exact = own_readings.iter().filter(|p| **p == pinyin).count() as u64;
+    let num_own_near = own_readings
+        .iter()
+        .filter(|p| {
+            **p != pinyin
+                && p.chars()
+                    .filter(|cha| !cha.is_ascii_digit())
+                    .eq(toneless.chars())
+        })
+        .count() as u64;
+    let mut exact = search_by_commonness(pers, "pinyin_compact", &pinyin, false, top + 1)?;
+    let num_exact = exact.num_hits;
+    exact.data.retain(|hit| is_other_word(&hit.doc));
+    exact.num_hits = num_exact.saturating_sub(num_own_exact);
+    exact.data.truncate(top);
+    // The exact homophones are also found by the toneless pinyin
+    let mut near = search_by_commonness(
+        pers,
+        "pinyin_toneless",
+        &toneless,
+        false,
+        top + (num_exact + num_own_near) as usize,
+    )?;
+    near.data.retain(|hit| {
+        is_other_word(&hit.doc) && hit.doc["pinyin_compact"].as_str() != Some(pinyin.as_str())
+    });
+    near.num_hits = near.num_hits.saturating_sub(num_exact + num_own_near);
+    near.data.truncate(top);
+    Ok(Homophones {
+        pinyin,
+        exact,
+        near,
+    })
+}
+
+/// Position of a word in a related word, e.g. 電 in 電腦 (start), 閃電 (end) or 發電機 (middle)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelatedPosition {
+    Start,
+    End,
+    Middle,
+}
+
+impl RelatedPosition {
+    pub const ALL: [RelatedPosition; 3] = [
+        RelatedPosition::Start,
+        RelatedPosition::End,
+        RelatedPosition::Middle,
+    ];
+
+    fn regex(&self, word: &str) -> String {
+        match self {
+            RelatedPosition::Start => format!("{}.+", word),
+            RelatedPosition::End => format!(".+{}", word),
+            RelatedPosition::Middle => format!(".+{}.+", word),
+        }
+    }
+}
+
+/// Returns the most common entries (traditional), which start with, end with or contain the
+/// word, at most `limit` per group. Empty groups are skipped.
+pub fn related_words(word: &str, limit: usize) -> Vec<(RelatedPosition, Vec<String>)> {
+    let pers = &PERSISTENCE;
+    if !word.chars().all(is_chinese) {
+        return Vec::new();
+    }
+    let word: String = word.chars().map(to_traditional_chinese_variant).collect();
+    // Skips entries with multiple readings and words already listed in an earlier group, e.g.
+    // words starting and ending with the word
+    let mut seen = FnvHashSet::default();
+    RelatedPosition::ALL
+        .iter()
+        .filter_map(|position| {
+            let regex = position.regex(&word);
+            // The limit applies after skipping, so more hits are fetched until there are enough
+            let mut top = limit + seen.len();
+            let words = loop {
+                let res = match search_by_commonness(pers, "traditional", &regex, true, top) {
+                    Ok(res) => res,
+                    Err(e) => {
+                        error!("Error in related words search: {:?}", e);
+                        return None;
+                    }
+                };
+                let mut words: Vec<String> = Vec::new();
+                for word in res
+                    .data
+                    .iter()
+                    .filter_map(|hit| hit.doc["traditional"].as_str())
+                {
+                    if !seen.contains(word) && !words.iter().any(|el| el == word) {
+                        words.push(word.to_string());
+                    }
+                }
+                if words.len() >= limit || res.data.len() as u64 >= res.num_hits {
+                    words.truncate(limit);
+                    break words;
+                }
+                top *= 2;
+            };
+            seen.extend(words.iter().cloned());
+            (!words.is_empty()).then_some((*position, words))
+        })
+        .collect()
+}
+
+pub fn run_search_veloci(
+    query: &str,
+    top: usize,
+    lang: Language,
+    mode: RankingMode,
+) -> Result<SearchResultWithDoc, VelociError> {
     debug_time!("SearchTime");
     let pers = &PERSISTENCE;
 
@@ -94,6 +530,8 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
     info!("Query {:?}", query);
 
     let tag_filter = get_tag_filter(&mut query)?;
+    let syllable_filters = get_syllable_filters(&mut query)?;
+    let explicit_kana = get_explicit_kana(&mut query);
     //dbg!(&query);
 
     let terms_from_query = || query.split_whitespace().filter(|el| !el.is_empty());
@@ -104,22 +542,24 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
         && terms_from_query().any(|term| !is_chinese_input(term));
 
     //let num_terms = terms_from_query().count();
-    let queries: Vec<SearchRequest> = terms_from_query()
+    let mut queries: Vec<SearchRequest> = terms_from_query()
         .flat_map(|term| {
-            let fields = if is_chinese_input(term) {
-                vec!["simplified", "traditional"]
+            let fields: Vec<String> = if is_chinese_input(term) {
+                vec!["simplified".to_string(), "traditional".to_string()]
             } else {
-                vec![
-                    "simplified",
-                    "traditional",
+                let mut fields = vec![
+                    "simplified".to_string(),
+                    "traditional".to_string(),
                     //"pinyin",
-                    "zhuyin",
+                    "zhuyin".to_string(),
                     //"pinyin_pretty",
-                    "pinyin_search[]",
-                    "tags[]",
-                    "meanings[]",
-                    "meanings_de[]",
-                ]
+                    "pinyin_search[]".to_string(),
+                    "tags[]".to_string(),
+                    "examples[].translation".to_string(),
+                ];
+                fields.extend(Language::all().iter().map(Language::meanings_path));
+                fields.retain(|path| is_searchable(path));
+                fields
             };
 
             let terms: Vec<(String, bool)> = if is_chinese_input(term) {
@@ -160,6 +600,7 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
                         terms: vec![term],
                         path: path.to_string(),
                         is_regex,
+                        boost: lang.meanings_boost(&path).map(Into::into),
                         //levenshtein_distance: Some(0),
                         ..Default::default()
                     })
@@ -168,6 +609,80 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
         })
         .collect();
 
+    // Japanese readings of the characters, e.g. ガク, がく, romaji:gaku or gaku find 學.
+    // Most english and pinyin words are valid romaji too (e.g. "home" is ほめ), so plain romaji is
+    // only tried for unknown words and ranked lower.
+    if is_searchable("kana_search[]") {
+        queries.extend(
+            explicit_kana
+                .into_iter()
+                .map(|kana| (kana, None))
+                .chain(terms_from_query().filter_map(|term| {
+                    if kana::is_kana(term) {
+                        Some((kana::to_hiragana(term), None))
+                    } else if term.chars().all(|cha| cha.is_ascii_alphabetic()) {
+                        // The index is only checked for terms which are valid romaji
+                        let kana = kana::romaji_to_hiragana(term)?;
+                        (!is_known_word(pers, term)).then_some((kana, Some(0.3f32)))
+                    } else {
+                        None
+                    }
+                }))
+                .map(|(kana, boost)| {
+                    SearchRequest::Search(RequestSearchPart {
+                        terms: vec![kana],
+                        path: "kana_search[]".to_string(),
+                        boost: boost.map(Into::into),
+                        ..Default::default()
+                    })
+                }),
+        );
+    }
+
+    // Example sentences containing the chinese term, ranked below the headword matches
+    queries.extend(
+        terms_from_query()
+            .filter(|term| is_chinese_input(term))
+            .flat_map(|term| {
+                let traditional: String =
+                    term.chars().map(to_traditional_chinese_variant).collect();
+                [
+                    ("examples[].traditional", traditional),
+                    ("examples[].simplified", term.to_string()),
+                ]
+            })
+            .filter(|(path, _)| is_searchable(path))
+            .map(|(path, term)| {
+                SearchRequest::Search(RequestSearchPart {
+                    terms: vec![format!(".*{}.*", term)],
+                    path: path.to_string(),
+                    is_regex: true,
+                    boost: Some(0.3f32.into()),
+                    ..Default::default()
+                })
+            }),
+    );
+
+    // Split separable verbs, e.g. 睡了一個覺 finds 睡覺. The regex queries above need the
+    // characters to be contiguous.
+    queries.extend(
+        terms_from_query()
+            .filter(|term| is_chinese_input(term))
+            .flat_map(|term| {
+                let term: String = term.chars().map(to_traditional_chinese_variant).collect();
+                separable::find_split_usages(&term)
+            })
+            .map(|word| {
+                SearchRequest::Search(RequestSearchPart {
+                    terms: vec![word],
+                    path: "traditional".to_string(),
+                    boost: Some(2.0f32.into()),
+                    ..Default::default()
+                })
+            }),
+    );
+
+    let has_terms = !queries.is_empty();
     // Just search for the tags in case there's no search term and only tag filters
     let search_request: search::SearchRequest = if queries.is_empty() && tag_filter.is_some() {
         tag_filter.as_ref().cloned().unwrap()
@@ -177,21 +692,29 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
             options: Default::default(),
         })
     };
+    // The tone pattern and rhyme must match in addition to the search terms
+    let search_request = if syllable_filters.is_empty() {
+        search_request
+    } else {
+        let mut queries = syllable_filters;
+        if has_terms {
+            queries.push(search_request);
+        }
+        SearchRequest::And(search::SearchTree {
+            queries,
+            options: Default::default(),
+        })
+    };
 
     //println!("{}", serde_json::to_string_pretty(&search_request).unwrap());
 
     let terms = terms_from_query().collect::<Vec<_>>();
-    let phrase_queries = generate_phrase_queries_simple(
-        pers,
-        &terms,
-        vec![
-            "meanings[]".to_string(),
-            "meanings_de[]".to_string(),
-            "pinyin".to_string(),
-            "pinyin_search[]".to_string(),
-        ],
-    )
-    .unwrap();
+    let mut phrase_fields: Vec<String> = Language::all()
+        .iter()
+        .map(Language::meanings_path)
+        .collect();
+    phrase_fields.extend(["pinyin".to_string(), "pinyin_search[]".to_string()]);
+    let phrase_queries = generate_phrase_queries_simple(pers, &terms, phrase_fields).unwrap();
     //println!(
     //"phrase_queries {}",
     //serde_json::to_string_pretty(&phrase_queries).unwrap()
@@ -203,30 +726,46 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
         Some(phrase_queries)
     };
 
+    let mut boosts = vec![
+        RequestBoostPart {
+            path: "commonness_boost".to_string(),
+            boost_fun: Some(search::BoostFunction::Add),
+            ..Default::default()
+        },
+        RequestBoostPart {
+            path: "tocfl_level".to_string(), // levels 1-7. level1 is very common
+            expression: Some("10 / $SCORE".to_string()),
+            ..Default::default()
+        },
+    ];
+    if is_searchable("hsk_level") {
+        boosts.push(RequestBoostPart {
+            path: "hsk_level".to_string(), // levels 1-9
+            expression: Some("10 / $SCORE".to_string()),
+            ..Default::default()
+        });
+    }
+    if let Some(path) = mode.boost_path() {
+        boosts.push(RequestBoostPart {
+            path: path.to_string(),
+            boost_fun: Some(search::BoostFunction::Add),
+            ..Default::default()
+        });
+    }
+
     let requesto = search::Request {
         why_found: true,
         filter: tag_filter.map(Box::new),
         search_req: Some(search_request),
         phrase_boosts,
-        boost: Some(vec![
-            RequestBoostPart {
-                path: "commonness_boost".to_string(),
-                boost_fun: Some(search::BoostFunction::Add),
-                ..Default::default()
-            },
-            RequestBoostPart {
-                path: "tocfl_level".to_string(), // levels 1-7. level1 is very common
-                expression: Some("10 / $SCORE".to_string()),
-                ..Default::default()
-            },
-        ]),
+        boost: Some(boosts),
         top: Some(top),
         ..Default::default()
     };
 
     let res = search::to_search_result(
         pers,
-        search::search(requesto.clone(), pers).expect("search error"),
+        search::search(requesto.clone(), pers)?,
         &requesto.select,
     );
     //println!("{}", serde_json::to_string_pretty(&res).unwrap());
@@ -239,24 +778,51 @@ pub fn run_search_veloci(query: &str, top: usize) -> Result<SearchResultWithDoc,
 mod tests {
     use super::*;
 
+    #[test]
+    fn missing_optional_fields() {
+        // E.g. an index built without HanDeDict has no examples
+        assert!(!has_field("examples[].missing"));
+        assert!(is_searchable("meanings[]"));
+        for path in OPTIONAL_FIELDS {
+            assert_eq!(is_searchable(path), has_field(path));
+        }
+        // Only the fields in the index are searched
+        assert!(run_search_veloci("學 school", 3, Language::EN, RankingMode::Modern).is_ok());
+        assert!(run_search_veloci("gaku", 3, Language::EN, RankingMode::Modern).is_ok());
+    }
+
     #[test]
     fn mixed_search_hits() {
-        let res = run_search_veloci("分 xiang3", 3).unwrap();
+        let res = run_search_veloci("分 xiang3", 3, Language::EN, RankingMode::Modern).unwrap();
         assert_eq!(res.data[0].doc["traditional"], "分享");
     }
 
     #[test]
     fn test_fen() {
-        let res = run_search_veloci("分", 3).unwrap();
+        let res = run_search_veloci("分", 3, Language::EN, RankingMode::Modern).unwrap();
         assert_eq!(res.data[0].doc["traditional"], "分");
         assert_eq!(res.data[0].doc["pinyin"], "fen1");
     }
 
+    #[test]
+    fn japanese_reading_search() {
+        for reading in ["ガク", "がく", "gaku", "romaji:gaku", "kana:ガク"] {
+            let res = run_search_veloci(reading, 10, Language::EN, RankingMode::Modern).unwrap();
+            assert!(
+                res.data
+                    .iter()
+                    .any(|hit| hit.doc["traditional"] == "學" || hit.doc["simplified"] == "学"),
+                "Failed for reading: {}",
+                reading
+            );
+        }
+    }
+
     #[test]
     fn pinyin_search() {
         let pinyins = vec!["xiawu", "xia wu", "xiàwǔ", "xià wǔ", "xia4 wu3", "xia4wu3"];
         for pinyin in pinyins {
-            let res = run_search_veloci(pinyin, 3).unwrap();
+            let res = run_search_veloci(pinyin, 3, Language::EN, RankingMode::Modern).unwrap();
             assert_eq!(
                 res.data[0].doc["traditional"], "下午",
                 "Failed for pinyin input: {}",
@@ -264,4 +830,97 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn classical_mode_ranks_classical_characters_higher() {
+        let res = run_search_veloci("zhi1", 3, Language::EN, RankingMode::Classical).unwrap();
+        assert_eq!(res.data[0].doc["traditional"], "之");
+    }
+
+    #[test]
+    fn separable_verb_split_usage() {
+        let res = run_search_veloci("睡了一個覺", 3, Language::EN, RankingMode::Modern).unwrap();
+        assert_eq!(res.data[0].doc["traditional"], "睡覺");
+    }
+
+    #[test]
+    fn reduplication_rewrite() {
+        // 研究研究 has no entry, unlike 高高興興
+        let (query, rewrites) = rewrite_query("研究研究");
+        assert_eq!(query, "研究");
+        assert_eq!(rewrites[0].reason, RewriteReason::Reduplication("ABAB"));
+        // 媽媽 has its own entry
+        let (query, rewrites) = rewrite_query("媽媽");
+        assert_eq!(query, "媽媽");
+        assert!(rewrites.is_empty());
+    }
+
+    #[test]
+    fn sentence_segmentation() {
+        let segments = segment_query("我想去台北");
+        assert_eq!(segments[0].1, vec!["我", "想", "去", "台北"]);
+        assert!(segment_query("台北").is_empty());
+    }
+
+    #[test]
+    fn related_words_by_position() {
+        let related = related_words("電", 5);
+        let (position, words) = &related[0];
+        assert_eq!(*position, RelatedPosition::Start);
+        assert!(words.iter().any(|word| word == "電腦"));
+    }
+
+    #[test]
+    fn homophones() {
+        let homophones = find_homophones("shi4", 10).unwrap();
+        assert!(homophones
+            .exact
+            .data
+            .iter()
+            .all(|hit| hit.doc["pinyin_compact"] == "shi4"));
+        assert!(homophones
+            .near
+            .data
+            .iter()
+            .any(|hit| hit.doc["traditional"] == "十"));
+        let homophones = find_homophones("電腦", 10).unwrap();
+        assert_eq!(homophones.pinyin, "dian4nao3");
+        // The link on the card passes the headword with the reading
+        let homophones = find_homophones("電腦 dian4 nao3", 10).unwrap();
+        assert_eq!(homophones.pinyin, "dian4nao3");
+        assert!(homophones
+            .exact
+            .data
+            .iter()
+            .all(|hit| hit.doc["traditional"] != "電腦"));
+    }
+
+    #[test]
+    fn tone_pattern_search() {
+        let res =
+            run_search_veloci("tones:4-3 #TOCFL1", 10, Language::EN, RankingMode::Modern).unwrap();
+        assert!(res.data.iter().any(|hit| hit.doc["traditional"] == "下午"));
+        assert!(res.data.iter().all(|hit| hit.doc["tone_pattern"] == "43"));
+    }
+
+    #[test]
+    fn rhyme_search() {
+        let res = run_search_veloci("rhyme:ang", 100, Language::EN, RankingMode::Modern).unwrap();
+        assert!(!res.data.is_empty());
+        assert!(res.data.iter().all(|hit| {
+            let rhyme = hit.doc["rhyme"].as_str().unwrap();
+            ["ang", "iang", "uang"].contains(&rhyme)
+        }));
+        assert!(res.data.iter().any(|hit| hit.doc["rhyme"] == "iang"));
+    }
+
+    #[test]
+    fn inflection_rewrite() {
+        let (query, rewrites) = rewrite_query("沒吃過");
+        assert_eq!(query, "吃");
+        assert_eq!(rewrites[0].reason, RewriteReason::Inflection);
+        // 完了 and 不好 have their own entries
+        assert_eq!(rewrite_query("完了").0, "完了");
+        assert_eq!(rewrite_query("不好").0, "不好");
+    }
 }
@@ -0,0 +1,37 @@
+//! Segmentation of chinese text without spaces into dictionary words, e.g. 我想去台北 into
+//! 我 + 想 + 去 + 台北. Used for queries, which have no entry as a whole, e.g. pasted sentences.
+
+/// Longest word, which is looked up
+const MAX_WORD_LEN: usize = 8;
+
+/// Forward maximum matching: takes the longest word at each position, for which `is_word` returns
+/// true. Characters without a word are kept as single parts.
+pub fn segment(text: &str, is_word: impl Fn(&str) -> bool) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let max_end = (start + MAX_WORD_LEN).min(chars.len());
+        let end = (start + 2..=max_end)
+            .rev()
+            .find(|end| is_word(&chars[start..*end].iter().collect::<String>()))
+            .unwrap_or(start + 1);
+        parts.push(chars[start..end].iter().collect());
+        start = end;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment() {
+        let words = ["台北", "台北市", "想去"];
+        let is_word = |word: &str| words.contains(&word);
+        assert_eq!(segment("我想去台北", is_word), vec!["我", "想去", "台北"]);
+        assert_eq!(segment("台北市", is_word), vec!["台北市"]);
+        assert_eq!(segment("我", is_word), vec!["我"]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Separable verbs (離合詞) are often split in sentences, e.g. 睡了一個覺 for 睡覺 or 幫我的忙
+//! for 幫忙. The search finds the base word for such split usages.
+//!
+//! The verbs are embedded from `separable_verbs.txt` of `create_json`, with the split point
+//! marked by `|`, e.g. `睡|覺`.
+
+use once_cell::sync::Lazy;
+
+use crate::search::is_chinese;
+
+/// Maximum number of characters between the two parts, e.g. 了一個 in 睡了一個覺
+const MAX_GAP: usize = 5;
+
+/// Characters allowed between the two parts: aspect particles and complements, numbers, measure
+/// words, pronouns with 的 and some degree words, e.g. 了一個 in 睡了一個覺 or 我的 in 幫我的忙.
+/// Other words between the parts are usually a different sentence structure, e.g. 幫派很忙.
+const GAP_CHARS: &str = "了過过著着完好一二兩两三幾几半個个次回頓顿場场趟通張张點点些會会兒儿\
+                         什麼么的我你他她它們们很大小多";
+
+static SEPARABLE_VERBS: Lazy<Vec<SeparableVerb>> = Lazy::new(|| {
+    parse_separable_verbs(include_str!(
+        "../../create_db/create_json/separable_verbs.txt"
+    ))
+});
+
+#[derive(Debug, Clone, PartialEq)]
+struct SeparableVerb {
+    first: Vec<char>,
+    second: Vec<char>,
+}
+
+impl SeparableVerb {
+    fn word(&self) -> String {
+        self.first.iter().chain(self.second.iter()).collect()
+    }
+}
+
+fn parse_separable_verbs(text: &str) -> Vec<SeparableVerb> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (first, second) = line.split_once('|')?;
+            Some(SeparableVerb {
+                first: first.chars().collect(),
+                second: second.chars().collect(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the separable verbs used split in the (traditional) text, e.g. 睡覺 for 睡了一個覺.
+/// Unsplit usages like 睡覺 are found by the regular search.
+pub fn find_split_usages(text: &str) -> Vec<String> {
+    find_split_usages_in(&SEPARABLE_VERBS, text)
+}
+
+fn find_split_usages_in(verbs: &[SeparableVerb], text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    verbs
+        .iter()
+        .filter(|verb| {
+            (0..chars.len())
+                .filter(|start| chars[*start..].starts_with(&verb.first))
+                .any(|start| {
+                    let gap_start = start + verb.first.len();
+                    // The gap may only contain particle-like characters, no other words or
+                    // punctuation
+                    (gap_start + 1..=gap_start + MAX_GAP)
+                        .take_while(|end| *end <= chars.len() && is_gap_char(chars[end - 1]))
+                        .any(|end| chars[end..].starts_with(&verb.second))
+                })
+        })
+        .map(SeparableVerb::word)
+        .collect()
+}
+
+fn is_gap_char(cha: char) -> bool {
+    is_chinese(cha) && GAP_CHARS.contains(cha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_split_usages() {
+        let verbs = parse_separable_verbs("# comment\n睡|覺\n幫|忙\n見|面\n");
+        assert_eq!(find_split_usages_in(&verbs, "睡了一個覺"), vec!["睡覺"]);
+        assert_eq!(find_split_usages_in(&verbs, "幫我的忙"), vec!["幫忙"]);
+        // Not split
+        assert!(find_split_usages_in(&verbs, "睡覺").is_empty());
+        // Too far apart
+        assert!(find_split_usages_in(&verbs, "見過很多很多人的面").is_empty());
+        // Not particle-like
+        assert!(find_split_usages_in(&verbs, "幫派很忙").is_empty());
+    }
+
+    #[test]
+    fn test_embedded_separable_verbs() {
+        assert!(SEPARABLE_VERBS.iter().any(|verb| verb.word() == "睡覺"));
+        assert_eq!(find_split_usages("洗了個澡"), vec!["洗澡"]);
+    }
+}